@@ -1,11 +1,17 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::env::Args;
+use std::collections::{HashMap, VecDeque};
+use std::fs::read_to_string;
+use std::io::{stdin, Read};
 use std::iter::Peekable;
+use std::path::Path;
 use std::rc::Rc;
 use std::str::Chars;
+use std::time::Duration;
 
-use super::error::{ArgError, FluxError};
+use super::error::{ArgError, ErrorFormat};
+use super::script::parse_script;
+use crate::processing::filetype::Type;
+use crate::util::progress::ProgressTarget;
 
 mod flag {
     use std::cell::LazyCell;
@@ -18,15 +24,36 @@ mod flag {
     pub const FLAG_INPUT: &'static str = "input";
     pub const FLAG_IMAGE_PAGE_LIMIT: &'static str = "page-limit";
     pub const FLAG_INPUT_RESOLUTION_LIMIT: &'static str = "res-limit";
+    pub const FLAG_FRAME_RATE_LIMIT: &'static str = "frame-rate-limit";
+    pub const FLAG_VIDEO_TIME_LIMIT: &'static str = "video-time-limit";
+    pub const FLAG_PIXEL_LIMIT: &'static str = "pixel-limit";
+    pub const FLAG_SVG_DENSITY: &'static str = "svg-density";
+    pub const FLAG_SVG_SIZE: &'static str = "svg-size";
+    pub const FLAG_TIMEOUT: &'static str = "timeout";
+    pub const FLAG_OPERATION_TIMEOUT: &'static str = "operation-timeout";
+    pub const FLAG_VALIDATE: &'static str = "validate";
+    pub const FLAG_PROFILE: &'static str = "profile";
+    pub const FLAG_PROGRESS: &'static str = "progress";
+    pub const FLAG_SEED: &'static str = "seed";
+    pub const FLAG_DOCTOR: &'static str = "doctor";
     pub const FLAG_DISABLE_VIDEO_SUPPORT: &'static str = "disable-video-decode";
     pub const FLAG_IMAGE_INFO: &'static str = "info";
     pub const FLAG_VERSION: &'static str = "version";
+    pub const FLAG_OUTPUT_FORMAT: &'static str = "format";
+    pub const FLAG_OUTPUT_QUALITY: &'static str = "quality";
+    pub const FLAG_LIST_OPERATIONS: &'static str = "list-operations";
+    pub const FLAG_HELP: &'static str = "help";
+    pub const FLAG_SCRIPT: &'static str = "script";
+    pub const FLAG_PEEK: &'static str = "peek";
+    pub const FLAG_VIDEO_DECODE_DISABLED_MESSAGE: &'static str = "video-decode-disabled-message";
+    pub const FLAG_ERROR_FORMAT: &'static str = "error-format";
 
     pub const FLAG_MAPPER: LazyCell<HashMap<&'static str, &'static str>> = LazyCell::new(|| {
         let mut h = HashMap::new();
         h.insert("o", FLAG_OPERATION);
         h.insert("i", FLAG_INPUT);
         h.insert("v", FLAG_VERSION);
+        h.insert("h", FLAG_HELP);
         h
     });
 }
@@ -35,13 +62,84 @@ mod flag {
 #[derive(Debug)]
 pub enum ArgType {
     InputPath(String),
-    Operation(String),
+    /// An operation name, and its options.
+    Operation(String, HashMap<String, String>),
+    /// Moves the most recent item in the input queue to a named slot.
+    StoreAs(String),
+    /// Pushes a copy of a named item to the input queue.
+    Load(String),
     OutputPath(String),
+    /// An output path that the next item in the input queue is written to without removing it
+    /// from the queue.
+    PeekOutputPath(String),
+    OutputFormat(Type),
+    /// Quality of lossy outputs, from 1 to 100.
+    OutputQuality(u8),
     ImagePageLimit(u64),
     InputResolutionLimit((u64, u64)),
+    FrameRateLimit(u64),
+    VideoTimeLimit(Duration),
+    PixelLimit(u64),
+    /// Density SVGs are rendered at, in dots per inch.
+    SvgDensity(f64),
+    /// Size SVGs are rendered to fit within.
+    SvgSize((u64, u64)),
+    Timeout(Duration),
+    OperationTimeout(Duration),
+    Seed(u64),
     VideoSupportDisabled,
+    VideoDecodeDisabledMessage(String),
+    ErrorFormat(ErrorFormat),
+    Profile,
+    Progress(ProgressTarget),
     Info,
     Version,
+    ListOperations,
+    Doctor,
+    OperationHelp(String),
+    Validate,
+}
+impl ArgType {
+    /// The argument as it would have been typed, e.g. `--info` or `-o invert`, for use in error
+    /// messages.
+    pub fn describe(&self) -> String {
+        use flag::*;
+
+        let name = match self {
+            ArgType::InputPath(path) => return format!("-i {path}"),
+            ArgType::Operation(name, _) => return format!("-o {name}"),
+            ArgType::StoreAs(name) => return format!("as={name}"),
+            // only scripts can load a named input
+            ArgType::Load(name) => return format!("load {name}"),
+            ArgType::OutputPath(path) => return format!("output path {path}"),
+            ArgType::PeekOutputPath(_) => FLAG_PEEK,
+            ArgType::OutputFormat(_) => FLAG_OUTPUT_FORMAT,
+            ArgType::OutputQuality(_) => FLAG_OUTPUT_QUALITY,
+            ArgType::ImagePageLimit(_) => FLAG_IMAGE_PAGE_LIMIT,
+            ArgType::InputResolutionLimit(_) => FLAG_INPUT_RESOLUTION_LIMIT,
+            ArgType::FrameRateLimit(_) => FLAG_FRAME_RATE_LIMIT,
+            ArgType::VideoTimeLimit(_) => FLAG_VIDEO_TIME_LIMIT,
+            ArgType::PixelLimit(_) => FLAG_PIXEL_LIMIT,
+            ArgType::SvgDensity(_) => FLAG_SVG_DENSITY,
+            ArgType::SvgSize(_) => FLAG_SVG_SIZE,
+            ArgType::Timeout(_) => FLAG_TIMEOUT,
+            ArgType::OperationTimeout(_) => FLAG_OPERATION_TIMEOUT,
+            ArgType::Seed(_) => FLAG_SEED,
+            ArgType::VideoSupportDisabled => FLAG_DISABLE_VIDEO_SUPPORT,
+            ArgType::VideoDecodeDisabledMessage(_) => FLAG_VIDEO_DECODE_DISABLED_MESSAGE,
+            ArgType::ErrorFormat(_) => FLAG_ERROR_FORMAT,
+            ArgType::Profile => FLAG_PROFILE,
+            ArgType::Progress(_) => FLAG_PROGRESS,
+            ArgType::Info => FLAG_IMAGE_INFO,
+            ArgType::Version => FLAG_VERSION,
+            ArgType::ListOperations => FLAG_LIST_OPERATIONS,
+            ArgType::Doctor => FLAG_DOCTOR,
+            ArgType::OperationHelp(_) => FLAG_HELP,
+            ArgType::Validate => FLAG_VALIDATE,
+        };
+
+        format!("{FLAG_LH_PREFIX}{name}")
+    }
 }
 
 /// Internal metadata and stateful information used by the argument parser.
@@ -57,28 +155,37 @@ impl ArgsMetaInternal {
     }
 }
 
-/// Accepts incoming std::env::Args and steps through them as needed.
+/// Accepts incoming arguments (such as std::env::Args) and steps through them as needed.
 pub struct ArgsHandler {
-    args: Rc<RefCell<Args>>,
+    args: Rc<RefCell<Peekable<Box<dyn Iterator<Item = String>>>>>,
+    /// Already-parsed arguments (e.g., from a script) to return before consuming more of `args`.
+    pending: Rc<RefCell<VecDeque<ArgType>>>,
     meta: Rc<RefCell<ArgsMetaInternal>>,
 }
 impl ArgsHandler {
-    pub fn new(args: Args) -> Self {
-        let args = Rc::new(RefCell::new(args));
-
+    pub fn new(args: impl IntoIterator<Item = String> + 'static) -> Self {
         // skip first arg
-        let mut b = args.borrow_mut();
-        let _ = b.next();
-        drop(b);
+        Self::without_program_name(args.into_iter().skip(1))
+    }
+
+    /// Creates an ArgsHandler for arguments that do not start with the program name.
+    pub fn without_program_name(args: impl IntoIterator<Item = String> + 'static) -> Self {
+        let args: Box<dyn Iterator<Item = String>> = Box::new(args.into_iter());
+        let args = Rc::new(RefCell::new(args.peekable()));
 
         ArgsHandler {
             args,
+            pending: Rc::new(RefCell::new(VecDeque::new())),
             meta: Rc::new(RefCell::new(ArgsMetaInternal::new())),
         }
     }
 
     /// Parses the next argument. Consumes arguments as this function is called.
     pub fn parse_next(&self) -> Result<ArgType, ArgError> {
+        if let Some(pending) = self.pending.borrow_mut().pop_front() {
+            return Ok(pending);
+        }
+
         let first = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
 
         let flag_full_name = if first.starts_with(flag::FLAG_LH_PREFIX) {
@@ -92,9 +199,15 @@ impl ArgsHandler {
             None
         };
 
-        // if not a flag, it should be an output path
+        // if not a flag, it should be an output path, unless it names the previous result
         if let Some(name) = flag_full_name {
             self.handle_flag(name)
+        } else if let Some(name) = first.strip_prefix("as=") {
+            if !is_valid_name(name) {
+                return Err(ArgError::FlagOptionParseError(format!("Invalid name {name}")));
+            }
+
+            Ok(ArgType::StoreAs(name.to_owned()))
         } else {
             Ok(ArgType::OutputPath(first))
         }
@@ -105,10 +218,22 @@ impl ArgsHandler {
         match flag {
             flag::FLAG_OPERATION => {
                 let op = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
-                Ok(ArgType::Operation(op))
+                let (name, options) = Self::parse_operation_name(&op)?;
+                Ok(ArgType::Operation(name, options))
             },
             flag::FLAG_INPUT => {
                 let input = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+
+                // name=path stores the input under a name instead of leaving it in the queue, unless
+                // a file is actually called that
+                if let Some((name, path)) = input.split_once('=')
+                    && is_valid_name(name)
+                    && !Path::new(&input).exists()
+                {
+                    self.pending.borrow_mut().push_front(ArgType::StoreAs(name.to_owned()));
+                    return Ok(ArgType::InputPath(path.to_owned()));
+                }
+
                 Ok(ArgType::InputPath(input))
             },
             flag::FLAG_IMAGE_PAGE_LIMIT => {
@@ -149,29 +274,165 @@ impl ArgsHandler {
 
                 Ok(ArgType::InputResolutionLimit((width, height)))
             },
+            flag::FLAG_FRAME_RATE_LIMIT => {
+                let limit = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let limit = limit
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|l| *l > 0)
+                    .ok_or(ArgError::FlagOptionParseError(format!("Invalid frame rate limit {limit}")))?;
+                Ok(ArgType::FrameRateLimit(limit))
+            },
+            flag::FLAG_VIDEO_TIME_LIMIT => {
+                let limit = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let limit = limit
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|l| Duration::try_from_secs_f64(l).ok())
+                    .ok_or(ArgError::FlagOptionParseError(format!("Invalid video time limit {limit}")))?;
+                Ok(ArgType::VideoTimeLimit(limit))
+            },
+            flag::FLAG_PIXEL_LIMIT => {
+                let limit = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let limit = limit
+                    .parse::<u64>()
+                    .map_err(|e| ArgError::FlagOptionParseError(format!("Invalid pixel limit {limit}: {e}")))?;
+                Ok(ArgType::PixelLimit(limit))
+            },
+            flag::FLAG_SVG_DENSITY => {
+                let density = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let density = density
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|d| d.is_finite() && *d > 0.0)
+                    .ok_or(ArgError::FlagOptionParseError(format!("Invalid SVG density {density}")))?;
+                Ok(ArgType::SvgDensity(density))
+            },
+            flag::FLAG_SVG_SIZE => {
+                let size = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let (width, height) = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse::<u64>().ok()?, h.parse::<u64>().ok()?)))
+                    .filter(|(w, h)| *w > 0 && *h > 0)
+                    .ok_or(ArgError::FlagOptionParseError(format!(
+                        "Invalid SVG size {size}, expected [width]x[height]"
+                    )))?;
+                Ok(ArgType::SvgSize((width, height)))
+            },
+            flag::FLAG_SEED => {
+                let seed = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let seed = seed
+                    .parse::<u64>()
+                    .map_err(|e| ArgError::FlagOptionParseError(format!("Invalid seed {seed}: {e}")))?;
+                Ok(ArgType::Seed(seed))
+            },
+            flag::FLAG_TIMEOUT | flag::FLAG_OPERATION_TIMEOUT => {
+                let timeout = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let timeout = timeout
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                    .ok_or(ArgError::FlagOptionParseError(format!("Invalid timeout {timeout}")))?;
+
+                if flag == flag::FLAG_TIMEOUT {
+                    Ok(ArgType::Timeout(timeout))
+                } else {
+                    Ok(ArgType::OperationTimeout(timeout))
+                }
+            },
+            flag::FLAG_OUTPUT_QUALITY => {
+                let quality = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let quality = quality
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|q| (1..=100).contains(q))
+                    .ok_or(ArgError::FlagOptionParseError(format!(
+                        "Invalid quality {quality}, expected a number from 1 to 100"
+                    )))?;
+                Ok(ArgType::OutputQuality(quality))
+            },
+            flag::FLAG_OUTPUT_FORMAT => {
+                let format = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let ty = Type::from_extension(&format).ok_or(ArgError::FlagOptionParseError(format!(
+                    "Unrecognised output format {format}"
+                )))?;
+                Ok(ArgType::OutputFormat(ty))
+            },
             flag::FLAG_DISABLE_VIDEO_SUPPORT => Ok(ArgType::VideoSupportDisabled),
+            flag::FLAG_VIDEO_DECODE_DISABLED_MESSAGE => {
+                let message = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                Ok(ArgType::VideoDecodeDisabledMessage(message))
+            },
+            flag::FLAG_ERROR_FORMAT => {
+                let format = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                match &format[..] {
+                    "text" => Ok(ArgType::ErrorFormat(ErrorFormat::Text)),
+                    "json" => Ok(ArgType::ErrorFormat(ErrorFormat::Json)),
+                    _ => Err(ArgError::FlagOptionParseError(format!(
+                        "Unrecognised error format {format}, expected text or json"
+                    ))),
+                }
+            },
             flag::FLAG_IMAGE_INFO => Ok(ArgType::Info),
             flag::FLAG_VERSION => Ok(ArgType::Version),
+            flag::FLAG_LIST_OPERATIONS => Ok(ArgType::ListOperations),
+            flag::FLAG_DOCTOR => Ok(ArgType::Doctor),
+            flag::FLAG_VALIDATE => Ok(ArgType::Validate),
+            flag::FLAG_PROFILE => Ok(ArgType::Profile),
+            flag::FLAG_PROGRESS => {
+                let target = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let target = ProgressTarget::parse(&target).ok_or(ArgError::FlagOptionParseError(format!(
+                    "Invalid progress target {target}: expected stderr or a file descriptor"
+                )))?;
+                Ok(ArgType::Progress(target))
+            },
+            flag::FLAG_PEEK => {
+                let path = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                Ok(ArgType::PeekOutputPath(path))
+            },
+            flag::FLAG_HELP => {
+                let op = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                Ok(ArgType::OperationHelp(op))
+            },
+            flag::FLAG_SCRIPT => {
+                let path = self.args.borrow_mut().next().ok_or(ArgError::ArgsExhausted)?;
+                let src = if path == "-" {
+                    let mut buf = String::new();
+                    stdin().read_to_string(&mut buf).map(|_| buf)
+                } else {
+                    read_to_string(&path)
+                }
+                .map_err(|e| ArgError::FlagOptionParseError(format!("Failed to read script {path}: {e}")))?;
+
+                self.pending.borrow_mut().extend(parse_script(&src)?);
+                self.parse_next()
+            },
             _ => Err(ArgError::UnrecognisedFlag(flag.to_owned())),
         }
     }
 
+    /// Whether every argument has been consumed.
+    pub fn is_exhausted(&self) -> bool {
+        self.pending.borrow().is_empty() && self.args.borrow_mut().peek().is_none()
+    }
+
     /// Produces a clone of this ArgsHandler, allowing for lookahead validation etc.
     pub fn fork(&self) -> Self {
         Self {
             args: self.args.clone(),
+            pending: self.pending.clone(),
             meta: self.meta.clone(),
         }
     }
 
     /// Parse format: operation[x=1:y=2:z=whatever]
-    pub fn parse_operation_name(operation: &str) -> Result<(String, HashMap<String, String>), FluxError> {
+    pub fn parse_operation_name(operation: &str) -> Result<(String, HashMap<String, String>), ArgError> {
         if operation.contains("[") && operation.chars().last() != Some('[') {
             if !operation.ends_with("]") {
-                return Err(FluxError::Args(ArgError::FlagOptionParseError(format!(
+                return Err(ArgError::FlagOptionParseError(format!(
                     "Flag options missing termination for {}",
                     operation
-                ))));
+                )));
             }
 
             let options_start = operation.find("[").unwrap();
@@ -180,20 +441,16 @@ impl ArgsHandler {
 
             let mut parsed_options = HashMap::new();
             for option in options {
-                let split = option
-                    .split_once("=")
-                    .ok_or(FluxError::Args(ArgError::FlagOptionParseError(format!(
-                        "Option \"{option}\" has a key, but no value"
-                    ))))?;
+                let split = option.split_once("=").ok_or(ArgError::FlagOptionParseError(format!(
+                    "Option \"{option}\" has a key, but no value"
+                )))?;
 
                 if split.0.is_empty() {
-                    return Err(FluxError::Args(ArgError::FlagOptionParseError(
-                        "Option key cannot be blank".to_owned(),
-                    )));
+                    return Err(ArgError::FlagOptionParseError("Option key cannot be blank".to_owned()));
                 } else if split.1.is_empty() {
-                    return Err(FluxError::Args(ArgError::FlagOptionParseError(format!(
+                    return Err(ArgError::FlagOptionParseError(format!(
                         "Option \"{option}\" has a key, but no value"
-                    ))));
+                    )));
                 }
 
                 parsed_options.insert(split.0.to_owned(), split.1.to_owned());
@@ -214,6 +471,12 @@ impl ArgsHandler {
     }
 }
 
+/// Whether `name` can be used to name an input or result. Names may contain letters, numbers, `-`
+/// and `_`.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
 struct OperationOptions<'a> {
     iter: Peekable<Chars<'a>>,
 }
@@ -0,0 +1,237 @@
+use std::fs::{create_dir_all, read, read_dir, write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
+use tracing::debug;
+
+use super::args::{ArgType, ArgsHandler};
+use super::error::{ArgError, ErrorReport, FluxError};
+use super::media_container::MediaContainer;
+use crate::processing::filetype::Type;
+use crate::processing::media_object::MediaObject;
+use crate::util::cancel;
+
+/// Options for `flux batch`.
+pub struct BatchOptions {
+    pub inputs: Vec<PathBuf>,
+    /// Output path for each input. `{stem}`, `{name}`, `{ext}` and `{index}` are replaced with the
+    /// input file name without its extension, the full input file name, the input extension, and
+    /// the index of the input respectively.
+    pub output_template: String,
+    /// Arguments run for every input, after pushing the input to the input queue.
+    pub steps: Vec<ArgType>,
+}
+impl BatchOptions {
+    /// Parses the arguments following `batch`: a glob pattern or directory, an output template, and
+    /// then the same arguments used to build a pipeline on the command line.
+    pub fn parse(args: Vec<String>) -> Result<Self, ArgError> {
+        let mut args = args.into_iter();
+        let pattern = args.next().ok_or(ArgError::ArgsExhausted)?;
+        let output_template = args.next().ok_or(ArgError::ArgsExhausted)?;
+
+        let inputs = find_inputs(&pattern)?;
+        if inputs.len() > 1 && !["{stem}", "{name}", "{index}"].iter().any(|p| output_template.contains(p)) {
+            return Err(ArgError::FlagOptionParseError(format!(
+                "Output template {output_template} would write every output to the same path; use {{stem}}, {{name}} or {{index}}"
+            )));
+        }
+
+        let handler = ArgsHandler::without_program_name(args);
+        let mut steps = vec![];
+        while !handler.is_exhausted() {
+            let step = handler.parse_next()?;
+            match step {
+                ArgType::InputPath(_)
+                | ArgType::Operation(..)
+                | ArgType::StoreAs(_)
+                | ArgType::Load(_)
+                | ArgType::OutputFormat(_)
+                | ArgType::OutputQuality(_)
+                | ArgType::ImagePageLimit(_)
+                | ArgType::InputResolutionLimit(_)
+                | ArgType::FrameRateLimit(_)
+                | ArgType::VideoTimeLimit(_)
+                | ArgType::PixelLimit(_)
+                | ArgType::SvgDensity(_)
+                | ArgType::SvgSize(_)
+                | ArgType::OperationTimeout(_)
+                | ArgType::Seed(_)
+                | ArgType::VideoSupportDisabled
+                | ArgType::VideoDecodeDisabledMessage(_)
+                | ArgType::ErrorFormat(_) => steps.push(step),
+                ArgType::Validate => {
+                    return Err(ArgError::FlagOptionParseError(
+                        "--validate cannot be used in batch mode".to_owned(),
+                    ));
+                },
+                // the timeout covers the whole batch, so it starts now
+                ArgType::Timeout(timeout) => cancel::set_global_deadline(Some(Instant::now() + timeout)),
+                other => {
+                    return Err(ArgError::FlagOptionParseError(format!(
+                        "{} cannot be used in batch mode",
+                        other.describe()
+                    )));
+                },
+            }
+        }
+
+        Ok(Self {
+            inputs,
+            output_template,
+            steps,
+        })
+    }
+}
+
+/// Every file in a directory (not including subdirectories), or every file matching a glob pattern.
+fn find_inputs(pattern: &str) -> Result<Vec<PathBuf>, ArgError> {
+    let err = |e: String| ArgError::FlagOptionParseError(format!("Failed to find inputs for {pattern}: {e}"));
+
+    let mut inputs = if Path::new(pattern).is_dir() {
+        read_dir(pattern)
+            .map_err(|e| err(e.to_string()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect::<Vec<_>>()
+    } else {
+        glob::glob(pattern)
+            .map_err(|e| err(e.to_string()))?
+            .filter_map(Result::ok)
+            .filter(|p| p.is_file())
+            .collect::<Vec<_>>()
+    };
+
+    if inputs.is_empty() {
+        return Err(err("no files found".to_owned()));
+    }
+
+    inputs.sort();
+    Ok(inputs)
+}
+
+#[derive(Serialize)]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BatchResult>,
+}
+
+#[derive(Serialize)]
+pub struct BatchResult {
+    pub input: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+/// Runs the pipeline on every input in parallel, each with its own `MediaContainer`. A failure
+/// only affects its own input.
+pub fn run_batch(options: &BatchOptions) -> BatchSummary {
+    let results = options
+        .inputs
+        .par_iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let output = render_template(&options.output_template, input, index);
+            debug!("Processing {} to {output}", input.display());
+
+            // rayon may run this on a thread that is part way through another item's operation, so
+            // make sure that operation's deadline does not apply here
+            match cancel::with_deadline(None, || run_item(options, input, &output)) {
+                Ok(()) => BatchResult {
+                    input: input.display().to_string(),
+                    output: Some(output),
+                    ok: true,
+                    error: None,
+                },
+                Err(e) => BatchResult {
+                    input: input.display().to_string(),
+                    output: None,
+                    ok: false,
+                    error: Some(ErrorReport::from(&e)),
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let succeeded = results.iter().filter(|r| r.ok).count();
+
+    BatchSummary {
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    }
+}
+
+fn run_item(options: &BatchOptions, input: &Path, output: &str) -> Result<(), FluxError> {
+    let mut container = MediaContainer::new();
+    container.push_input(MediaObject::Encoded(read(input)?));
+
+    let mut format = None;
+    for step in &options.steps {
+        match step {
+            ArgType::InputPath(path) => container.push_input(MediaObject::Encoded(read(path)?)),
+            ArgType::Operation(operation, op_options) => {
+                container.handle_operation(operation, op_options.clone())?
+            },
+            ArgType::StoreAs(name) => container.store_as(name.clone())?,
+            ArgType::Load(name) => container.load(name)?,
+            ArgType::OutputFormat(ty) => format = Some(*ty),
+            ArgType::OutputQuality(quality) => container.output_quality = Some(*quality),
+            ArgType::ImagePageLimit(lim) => container.limits.frame_limit = Some(*lim),
+            ArgType::InputResolutionLimit((w, h)) => container.limits.resolution_limit = Some((*w, *h)),
+            ArgType::FrameRateLimit(lim) => container.limits.frame_rate_limit = Some(*lim),
+            ArgType::VideoTimeLimit(lim) => container.limits.video_time_limit = Some(*lim),
+            ArgType::PixelLimit(lim) => container.limits.pixel_limit = Some(*lim),
+            ArgType::SvgDensity(density) => container.limits.svg_density = Some(*density),
+            ArgType::SvgSize((w, h)) => container.limits.svg_size = Some((*w, *h)),
+            ArgType::OperationTimeout(timeout) => container.operation_timeout = Some(*timeout),
+            // every input uses the same seed, so each output only depends on its own input
+            ArgType::Seed(seed) => container.set_seed(*seed),
+            ArgType::VideoSupportDisabled => container.limits.video_decode_permitted = false,
+            ArgType::VideoDecodeDisabledMessage(message) => {
+                container.limits.video_decode_disabled_message = Some(message.clone())
+            },
+            ArgType::ErrorFormat(_) => {},
+            other => {
+                return Err(FluxError::ParameterError(format!(
+                    "{} cannot be used in batch mode",
+                    other.describe()
+                )));
+            },
+        }
+    }
+
+    let format = format.or_else(|| {
+        Path::new(output)
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Type::from_extension)
+    });
+
+    let encoded = container.encode_next(format)?;
+    container.finish()?;
+
+    if let Some(parent) = Path::new(output).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        create_dir_all(parent)?;
+    }
+
+    write(output, encoded)?;
+    Ok(())
+}
+
+fn render_template(template: &str, input: &Path, index: usize) -> String {
+    let part = |s: Option<&std::ffi::OsStr>| s.and_then(|s| s.to_str()).unwrap_or_default().to_owned();
+
+    template
+        .replace("{stem}", &part(input.file_stem()))
+        .replace("{name}", &part(input.file_name()))
+        .replace("{ext}", &part(input.extension()))
+        .replace("{index}", &index.to_string())
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::operations::registry::OPERATIONS;
+use crate::util::cancel;
+use crate::util::owned_child::IntoOwnedChild;
+
+/// How long to wait for a probe (e.g. `ffmpeg -version`) before treating the tool as unavailable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something outside of flux that some operations need in order to run.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Dependency {
+    /// ffmpeg and ffprobe, which are also needed to decode and encode video.
+    Ffmpeg,
+    Gegl,
+    /// Docker, with the `paulfitz/makesweet` image pulled.
+    Makesweet,
+    /// libvips, through the shared objects built into `./build`.
+    Vips,
+    /// A file that must exist, relative to the working directory.
+    Asset(&'static str),
+}
+impl Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dependency::Ffmpeg => write!(f, "ffmpeg"),
+            Dependency::Gegl => write!(f, "gegl"),
+            Dependency::Makesweet => write!(f, "makesweet"),
+            Dependency::Vips => write!(f, "vips"),
+            Dependency::Asset(path) => write!(f, "{path}"),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Why the dependency is unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Results of probing each dependency, so that each is only probed once per process.
+static STATUS: LazyLock<Mutex<HashMap<Dependency, DependencyStatus>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Checks whether a dependency is available. The result is cached, so this is cheap to call before
+/// every operation.
+pub fn check(dependency: Dependency) -> DependencyStatus {
+    if let Some(status) = STATUS.lock().unwrap_or_else(|e| e.into_inner()).get(&dependency) {
+        return status.clone();
+    }
+
+    // probed without holding the lock, since this may take a while
+    let status = cancel::with_timeout(Some(PROBE_TIMEOUT), || probe(dependency));
+    STATUS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(dependency, status.clone());
+
+    status
+}
+
+fn probe(dependency: Dependency) -> DependencyStatus {
+    let result = match dependency {
+        Dependency::Ffmpeg => run_version("ffmpeg", &["-version"])
+            .and_then(|version| run_version("ffprobe", &["-version"]).map(|_| version))
+            // e.g. "ffmpeg version 6.1.1 Copyright ..."
+            .map(|v| v.split_whitespace().nth(2).map(str::to_owned)),
+        // older builds of gegl have no --version flag, so it only needs to run
+        Dependency::Gegl => run("gegl", &["--version"]).map(|output| {
+            output
+                .status
+                .success()
+                .then(|| first_line(&output.stdout).trim().to_owned())
+        }),
+        Dependency::Makesweet => {
+            if !Path::new("./makesweet").is_dir() {
+                Err("./makesweet directory not found".to_owned())
+            } else {
+                run_version(
+                    "docker",
+                    &["image", "inspect", "--format", "{{.Id}}", "paulfitz/makesweet"],
+                )
+                .map(|id| Some(id.trim().to_owned()))
+            }
+        },
+        Dependency::Vips => {
+            if crate::init() {
+                Ok(Some(crate::vips::vips_version()))
+            } else {
+                Err("libvips failed to initialise".to_owned())
+            }
+        },
+        Dependency::Asset(path) => {
+            if Path::new(path).exists() {
+                Ok(None)
+            } else {
+                Err(format!("{path} not found"))
+            }
+        },
+    };
+
+    let (available, version, error) = match result {
+        Ok(version) => (true, version, None),
+        Err(e) => (false, None, Some(e)),
+    };
+
+    DependencyStatus {
+        name: dependency.to_string(),
+        available,
+        version,
+        error,
+    }
+}
+
+/// Runs a program to completion, failing only if it could not be executed.
+fn run(program: &'static str, args: &[&str]) -> Result<Output, String> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute {program}: {e}"))?
+        .into_owned_child(program)
+        .wait_with_output()
+        .map_err(|e| format!("Failed to execute {program}: {e}"))
+}
+
+/// Runs a program, returning the first line of its output if it succeeds.
+fn run_version(program: &'static str, args: &[&str]) -> Result<String, String> {
+    let output = run(program, args)?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(first_line(&output.stdout))
+}
+
+fn first_line(output: &[u8]) -> String {
+    String::from_utf8_lossy(output)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+#[derive(Serialize, Debug)]
+pub struct UnavailableOperation {
+    pub name: &'static str,
+    /// The dependencies that are missing.
+    pub missing: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DoctorReport {
+    pub dependencies: Vec<DependencyStatus>,
+    /// Whether video inputs can be decoded, and video outputs encoded.
+    pub video: bool,
+    pub available_operations: Vec<&'static str>,
+    pub unavailable_operations: Vec<UnavailableOperation>,
+}
+
+/// Probes every dependency and works out which operations can run.
+pub fn doctor() -> DoctorReport {
+    let mut dependencies = vec![
+        Dependency::Ffmpeg,
+        Dependency::Gegl,
+        Dependency::Makesweet,
+        Dependency::Vips,
+    ];
+    for operation in OPERATIONS {
+        for dependency in operation.requires {
+            if !dependencies.contains(dependency) {
+                dependencies.push(*dependency);
+            }
+        }
+    }
+
+    let mut available_operations = vec![];
+    let mut unavailable_operations = vec![];
+    for operation in OPERATIONS {
+        let missing = operation
+            .requires
+            .iter()
+            .filter(|d| !check(**d).available)
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+
+        if missing.is_empty() {
+            available_operations.push(operation.name);
+        } else {
+            unavailable_operations.push(UnavailableOperation {
+                name: operation.name,
+                missing,
+            });
+        }
+    }
+
+    DoctorReport {
+        video: check(Dependency::Ffmpeg).available,
+        dependencies: dependencies.into_iter().map(check).collect(),
+        available_operations,
+        unavailable_operations,
+    }
+}
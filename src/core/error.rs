@@ -3,6 +3,7 @@ use std::num::ParseFloatError;
 use std::ops::FromResidual;
 
 use image::ImageError;
+use serde::Serialize;
 use thiserror::Error;
 use zip::result::ZipError;
 
@@ -14,6 +15,8 @@ pub enum ArgError {
     UnrecognisedFlag(String),
     #[error("Error parsing flag options: {0}")]
     FlagOptionParseError(String),
+    #[error("Error parsing script on line {0}: {1}")]
+    ScriptParseError(usize, String),
 }
 
 #[derive(Error, Debug)]
@@ -28,25 +31,172 @@ pub enum FluxError {
     UnsupportedFiletype,
     #[error("Input image is corrupt: {0}")]
     CorruptInput(String),
+    /// A file given in the arguments does not exist or cannot be accessed.
+    #[error("Failed to access file: {0}")]
+    FileAccess(String),
     #[error("Input parameter error: {0}")]
     ParameterError(String),
+    #[error("Unsupported output format: {0}")]
+    UnsupportedOutputFormat(String),
+    #[error("Pipeline is invalid: {0} problem(s) found")]
+    ValidationFailed(usize),
     #[error("{0} residual images after encoding")]
     ResidualImages(u64),
+    #[error("Timed out")]
+    Timeout,
+    #[error("Input exceeds the limit of {0} decoded pixels")]
+    PixelLimitExceeded(u64),
+    /// A limit imposed by a decoder rather than by flux, such as a maximum image size.
+    #[error("Input exceeds a decoder limit: {0}")]
+    DecoderLimitExceeded(String),
+    #[error("Operation {0} is unavailable: {1}")]
+    OperationUnavailable(String, String),
+    #[error("External tool failed: {0}")]
+    ExternalToolError(String),
     #[error("Error with input media: {0}")]
     InputMediaError(String),
     #[error("This operation only supports inputs with more than one frame.")]
     SinglePageMediaUnsupported,
-    #[error(
-        "Video support is enabled through voting, becoming a patron, or upgrading this server to a premium server through Assyst's Discord store."
-    )]
-    VideoDecodeDisabled,
+    /// Video decoding is not permitted. The message is set through `DecodeLimits`, since it depends
+    /// on why video decoding was disabled.
+    #[error("{0}")]
+    VideoDecodeDisabled(String),
     #[error("Error: {0}")]
     Other(String),
 }
 
+/// Broad category of an error. Each category exits the process with its own exit code.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    InvalidArgs,
+    UnsupportedInput,
+    CorruptInput,
+    LimitExceeded,
+    ExternalTool,
+    Internal,
+}
+impl ErrorCategory {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCategory::InvalidArgs => 2,
+            ErrorCategory::UnsupportedInput => 3,
+            ErrorCategory::CorruptInput => 4,
+            ErrorCategory::LimitExceeded => 5,
+            ErrorCategory::ExternalTool => 6,
+            ErrorCategory::Internal => 7,
+        }
+    }
+}
+
+impl ArgError {
+    /// Stable machine-readable code for this error. These do not change between versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ArgError::ArgsExhausted => "args_exhausted",
+            ArgError::UnrecognisedFlag(_) => "unrecognised_flag",
+            ArgError::FlagOptionParseError(_) => "flag_option_parse_error",
+            ArgError::ScriptParseError(..) => "script_parse_error",
+        }
+    }
+}
+
+impl FluxError {
+    /// Stable machine-readable code for this error. These do not change between versions.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FluxError::Args(e) => e.code(),
+            FluxError::NothingToDo => "nothing_to_do",
+            FluxError::ScriptError(_) => "script_error",
+            FluxError::UnsupportedFiletype => "unsupported_filetype",
+            FluxError::CorruptInput(_) => "corrupt_input",
+            FluxError::FileAccess(_) => "file_access",
+            FluxError::ParameterError(_) => "parameter_error",
+            FluxError::UnsupportedOutputFormat(_) => "unsupported_output_format",
+            FluxError::ValidationFailed(_) => "validation_failed",
+            FluxError::ResidualImages(_) => "residual_images",
+            FluxError::Timeout => "timeout",
+            FluxError::PixelLimitExceeded(_) => "pixel_limit_exceeded",
+            FluxError::DecoderLimitExceeded(_) => "decoder_limit_exceeded",
+            FluxError::ExternalToolError(_) => "external_tool_error",
+            FluxError::OperationUnavailable(..) => "operation_unavailable",
+            FluxError::InputMediaError(_) => "input_media_error",
+            FluxError::SinglePageMediaUnsupported => "single_page_media_unsupported",
+            FluxError::VideoDecodeDisabled(_) => "video_decode_disabled",
+            FluxError::Other(_) => "other",
+        }
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            FluxError::Args(_)
+            | FluxError::NothingToDo
+            | FluxError::ParameterError(_)
+            | FluxError::FileAccess(_)
+            | FluxError::UnsupportedOutputFormat(_)
+            | FluxError::ValidationFailed(_)
+            | FluxError::ResidualImages(_) => ErrorCategory::InvalidArgs,
+            FluxError::UnsupportedFiletype | FluxError::SinglePageMediaUnsupported => ErrorCategory::UnsupportedInput,
+            FluxError::CorruptInput(_) | FluxError::InputMediaError(_) => ErrorCategory::CorruptInput,
+            FluxError::VideoDecodeDisabled(_)
+            | FluxError::PixelLimitExceeded(_)
+            | FluxError::DecoderLimitExceeded(_)
+            | FluxError::Timeout => ErrorCategory::LimitExceeded,
+            FluxError::ExternalToolError(_) | FluxError::OperationUnavailable(..) => ErrorCategory::ExternalTool,
+            FluxError::ScriptError(_) | FluxError::Other(_) => ErrorCategory::Internal,
+        }
+    }
+}
+
+/// How errors are reported on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    Json,
+}
+impl ErrorFormat {
+    /// Looks for `--error-format` in the raw arguments, so that the format is known even if the
+    /// arguments fail to parse.
+    pub fn from_args(args: &[String]) -> Self {
+        match args
+            .windows(2)
+            .rev()
+            .find(|w| w[0] == "--error-format")
+            .map(|w| &w[1][..])
+        {
+            Some("json") => ErrorFormat::Json,
+            _ => ErrorFormat::Text,
+        }
+    }
+}
+
+/// Machine-readable description of an error, as printed with `--error-format json`.
+#[derive(Serialize, Debug)]
+pub struct ErrorReport {
+    pub code: &'static str,
+    pub category: ErrorCategory,
+    pub message: String,
+}
+impl From<&FluxError> for ErrorReport {
+    fn from(value: &FluxError) -> Self {
+        Self {
+            code: value.code(),
+            category: value.category(),
+            message: format!("{value:#}"),
+        }
+    }
+}
+
 impl From<std::io::Error> for FluxError {
     fn from(value: std::io::Error) -> Self {
-        Self::ScriptError(value.to_string())
+        // child processes are killed with TimedOut when the deadline passes, see `OwnedChild`
+        match value.kind() {
+            std::io::ErrorKind::TimedOut => Self::Timeout,
+            // most often a mistyped input or output path, which is not a bug
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied => Self::FileAccess(value.to_string()),
+            _ => Self::ScriptError(value.to_string()),
+        }
     }
 }
 
@@ -54,6 +204,8 @@ impl From<ImageError> for FluxError {
     fn from(value: ImageError) -> Self {
         match value {
             ImageError::Decoding(e) => FluxError::CorruptInput(e.to_string()),
+            ImageError::Limits(e) => FluxError::DecoderLimitExceeded(e.to_string()),
+            ImageError::Unsupported(_) => FluxError::UnsupportedFiletype,
             otherwise => FluxError::ScriptError(otherwise.to_string()),
         }
     }
@@ -82,3 +234,38 @@ impl From<ZipError> for FluxError {
         Self::Other(value.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use image::error::{ImageFormatHint, LimitError, LimitErrorKind, UnsupportedError};
+
+    use super::*;
+
+    fn exit_code(e: impl Into<FluxError>) -> u8 {
+        e.into().category().exit_code()
+    }
+
+    #[test]
+    fn missing_files_are_invalid_args() {
+        let missing = std::fs::read("/nonexistent/flux-input.png").unwrap_err();
+        assert_eq!(exit_code(missing), 2);
+        assert_eq!(exit_code(std::io::Error::from(ErrorKind::PermissionDenied)), 2);
+    }
+
+    #[test]
+    fn io_errors_keep_their_categories() {
+        assert_eq!(exit_code(std::io::Error::from(ErrorKind::TimedOut)), 5);
+        assert_eq!(exit_code(std::io::Error::from(ErrorKind::BrokenPipe)), 7);
+    }
+
+    #[test]
+    fn image_errors_are_not_internal() {
+        let limits = ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError));
+        assert_eq!(exit_code(limits), 5);
+
+        let unsupported = ImageError::Unsupported(UnsupportedError::from(ImageFormatHint::Unknown));
+        assert_eq!(exit_code(unsupported), 3);
+    }
+}
@@ -1,6 +1,7 @@
-use std::env::Args;
 use std::fs::{read, write};
-use std::io::{stdin, Read};
+use std::io::{stdin, stdout, Read, Write};
+use std::path::Path;
+use std::time::Instant;
 
 use anyhow::Context;
 use serde_json::to_string;
@@ -8,10 +9,15 @@ use tracing::debug;
 
 use crate::core::args::ArgType;
 use crate::core::media_container::MediaContainer;
+use crate::operations::registry::{find_operation, OPERATIONS};
+use crate::processing::filetype::Type;
 use crate::processing::media_object::MediaObject;
+use crate::util::{cancel, profile, progress};
 
 use super::args::ArgsHandler;
-use super::error::FluxError;
+use super::doctor::doctor;
+use super::error::{ArgError, FluxError};
+use super::validate::validate_pipeline;
 
 #[derive(PartialEq, Clone)]
 pub enum StepAction {
@@ -19,14 +25,47 @@ pub enum StepAction {
     InputConsumed,
     /// An operation was performed on all or some of the current inputs.
     OperationPerformed(String),
-    /// The final output file descriptor was written to. This terminates this Flux instance.
+    /// An input was moved between the input queue and a named slot.
+    QueueModified,
+    /// An output file descriptor was written to.
     OutputWritten,
+    /// Every argument has been actioned and all outputs were written. This terminates this Flux
+    /// instance.
+    Finished,
     /// Some meta proprty has been set.
     MetaPropertySet(&'static str),
     /// Information about the next input has been requested. Exit.
     MediaInfo,
     /// Version information was printed.
     PrintVersion,
+    /// Information about the supported operations was printed.
+    PrintOperations,
+    /// The availability of external dependencies was printed.
+    PrintDoctor,
+    /// The pipeline was validated without being run.
+    Validated,
+}
+impl StepAction {
+    /// Whether this action ends the Flux instance.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            StepAction::Finished
+                | StepAction::MediaInfo
+                | StepAction::PrintVersion
+                | StepAction::PrintOperations
+                | StepAction::PrintDoctor
+                | StepAction::Validated
+        )
+    }
+
+    /// Whether this action changed the inputs, as opposed to only setting up how they are handled.
+    pub fn modifies_inputs(&self) -> bool {
+        matches!(
+            self,
+            StepAction::InputConsumed | StepAction::OperationPerformed(_) | StepAction::QueueModified
+        )
+    }
 }
 
 /// Main stateful struct for the current Flux instance.
@@ -35,24 +74,51 @@ pub enum StepAction {
 pub struct Flux {
     args_handler: ArgsHandler,
     previous_action: Option<StepAction>,
+    /// Whether any step so far has modified the inputs, see `StepAction::modifies_inputs`.
+    inputs_modified: bool,
     media_container: MediaContainer,
+    /// Format of the next output, if set explicitly.
+    output_format: Option<Type>,
+    outputs_written: usize,
 }
 impl Flux {
-    pub fn new(args: Args) -> Self {
+    /// Creates a new Flux instance from command line style arguments. As with `std::env::args`, the
+    /// first argument is the program name and is skipped.
+    pub fn new(args: impl IntoIterator<Item = String> + 'static) -> Self {
         Self {
             args_handler: ArgsHandler::new(args),
             previous_action: None,
+            inputs_modified: false,
             media_container: MediaContainer::new(),
+            output_format: None,
+            outputs_written: 0,
+        }
+    }
+
+    /// Steps through this instance until a terminal action is reached.
+    pub fn run(&mut self) -> Result<StepAction, FluxError> {
+        loop {
+            let action = self.step()?;
+            if action.is_terminal() {
+                return Ok(action);
+            }
         }
     }
 
     /// Steps through this instance by consuming the next input argument(s) and actioning upon it.
     pub fn step(&mut self) -> Result<StepAction, FluxError> {
-        // if we already wrote the output theres nothing left to do
-        if self.previous_action == Some(StepAction::OutputWritten) {
+        // if we already wrote every output theres nothing left to do
+        if self.previous_action == Some(StepAction::Finished) {
             return Err(FluxError::NothingToDo);
         }
 
+        // running out of arguments is only an error if nothing was output
+        if self.outputs_written > 0 && self.args_handler.is_exhausted() {
+            self.media_container.finish()?;
+            self.previous_action = Some(StepAction::Finished);
+            return Ok(StepAction::Finished);
+        }
+
         let next_arg = self.args_handler.parse_next().map_err(|e| FluxError::Args(e))?;
 
         match next_arg {
@@ -63,20 +129,51 @@ impl Flux {
                 self.previous_action = Some(StepAction::InputConsumed);
                 self.args_handler.set_version_flag_valid(false);
             },
-            ArgType::Operation(operation) => {
-                // operation string also contains options in the form of "operation[x=1:y=2:z=whatever]"
-                self.media_container.handle_operation(operation.clone())?;
+            ArgType::Operation(operation, options) => {
+                self.media_container.handle_operation(&operation, options)?;
                 self.previous_action = Some(StepAction::OperationPerformed(operation));
                 self.args_handler.set_version_flag_valid(false);
             },
+            ArgType::StoreAs(name) => {
+                debug!("Storing result as {name}");
+                self.media_container.store_as(name)?;
+                self.previous_action = Some(StepAction::QueueModified);
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::Load(name) => {
+                debug!("Loading {name} to queue");
+                self.media_container.load(&name)?;
+                self.previous_action = Some(StepAction::QueueModified);
+                self.args_handler.set_version_flag_valid(false);
+            },
             ArgType::OutputPath(output) => {
                 debug!("Writing output to {output}");
-                // todo: support encoding for format based on file extension, and stdout
-                let encoded = self.media_container.encode_next()?;
-                write(output, encoded)?;
+                let format = self.take_output_format(&output);
+                let encoded = self.media_container.encode_next(format)?;
+                self.write_output(&output, &encoded)?;
+                self.outputs_written += 1;
+                self.previous_action = Some(StepAction::OutputWritten);
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::PeekOutputPath(output) => {
+                debug!("Writing copy of next input to {output}");
+                let format = self.take_output_format(&output);
+                let encoded = self.media_container.encode_peek(format)?;
+                self.write_output(&output, &encoded)?;
+                self.outputs_written += 1;
                 self.previous_action = Some(StepAction::OutputWritten);
                 self.args_handler.set_version_flag_valid(false);
             },
+            ArgType::OutputFormat(format) => {
+                self.output_format = Some(format);
+                self.previous_action = Some(StepAction::MetaPropertySet("format"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::OutputQuality(quality) => {
+                self.media_container.output_quality = Some(quality);
+                self.previous_action = Some(StepAction::MetaPropertySet("quality"));
+                self.args_handler.set_version_flag_valid(false);
+            },
             ArgType::ImagePageLimit(lim) => {
                 self.media_container.limits.frame_limit = Some(lim);
                 self.previous_action = Some(StepAction::MetaPropertySet("page-limit"));
@@ -87,11 +184,74 @@ impl Flux {
                 self.previous_action = Some(StepAction::MetaPropertySet("resolution-limit"));
                 self.args_handler.set_version_flag_valid(false);
             },
+            ArgType::FrameRateLimit(lim) => {
+                self.media_container.limits.frame_rate_limit = Some(lim);
+                self.previous_action = Some(StepAction::MetaPropertySet("frame-rate-limit"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::VideoTimeLimit(lim) => {
+                self.media_container.limits.video_time_limit = Some(lim);
+                self.previous_action = Some(StepAction::MetaPropertySet("video-time-limit"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::PixelLimit(lim) => {
+                self.media_container.limits.pixel_limit = Some(lim);
+                self.previous_action = Some(StepAction::MetaPropertySet("pixel-limit"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::SvgDensity(density) => {
+                self.media_container.limits.svg_density = Some(density);
+                self.previous_action = Some(StepAction::MetaPropertySet("svg-density"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::SvgSize((w, h)) => {
+                self.media_container.limits.svg_size = Some((w, h));
+                self.previous_action = Some(StepAction::MetaPropertySet("svg-size"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::Timeout(timeout) => {
+                cancel::set_global_deadline(Some(Instant::now() + timeout));
+                self.previous_action = Some(StepAction::MetaPropertySet("timeout"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::OperationTimeout(timeout) => {
+                self.media_container.operation_timeout = Some(timeout);
+                self.previous_action = Some(StepAction::MetaPropertySet("operation-timeout"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::Seed(seed) => {
+                self.media_container.set_seed(seed);
+                self.previous_action = Some(StepAction::MetaPropertySet("seed"));
+                self.args_handler.set_version_flag_valid(false);
+            },
             ArgType::VideoSupportDisabled => {
                 self.media_container.limits.video_decode_permitted = false;
                 self.previous_action = Some(StepAction::MetaPropertySet("video-decode-disabled"));
                 self.args_handler.set_version_flag_valid(false);
             },
+            ArgType::VideoDecodeDisabledMessage(message) => {
+                self.media_container.limits.video_decode_disabled_message = Some(message);
+                self.previous_action = Some(StepAction::MetaPropertySet("video-decode-disabled-message"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            // the error format is read from the raw arguments before running, since errors may
+            // occur before this flag is reached
+            ArgType::ErrorFormat(_) => {
+                self.previous_action = Some(StepAction::MetaPropertySet("error-format"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::Profile => {
+                // the report is printed by the caller once everything has finished, see
+                // `util::profile::report`
+                profile::enable();
+                self.previous_action = Some(StepAction::MetaPropertySet("profile"));
+                self.args_handler.set_version_flag_valid(false);
+            },
+            ArgType::Progress(target) => {
+                progress::enable(target)?;
+                self.previous_action = Some(StepAction::MetaPropertySet("progress"));
+                self.args_handler.set_version_flag_valid(false);
+            },
             ArgType::Info => {
                 let info = self.media_container.info()?;
                 let json = to_string(&info).context("Failed to serialize info output")?;
@@ -101,9 +261,7 @@ impl Flux {
             },
             ArgType::Version => {
                 if !self.args_handler.version_flag_valid() {
-                    return Err(FluxError::Args(super::error::ArgError::UnrecognisedFlag(
-                        "version".to_string(),
-                    )));
+                    return Err(FluxError::Args(ArgError::UnrecognisedFlag("version".to_string())));
                 }
 
                 let git_hash = option_env!("FLUX_GIT_HASH").unwrap_or("Unknown");
@@ -112,9 +270,57 @@ impl Flux {
                 println!("flux {version} (commit {git_hash})");
                 self.previous_action = Some(StepAction::PrintVersion)
             },
+            ArgType::ListOperations => {
+                let json = to_string(OPERATIONS).context("Failed to serialize operation list")?;
+                println!("{json}");
+                self.previous_action = Some(StepAction::PrintOperations);
+            },
+            ArgType::Doctor => {
+                let json = to_string(&doctor()).context("Failed to serialize doctor report")?;
+                println!("{json}");
+                self.previous_action = Some(StepAction::PrintDoctor);
+            },
+            ArgType::Validate => {
+                // inputs and operations before --validate would already have been run, whereas
+                // flags that only set properties, such as --error-format, can come first
+                if self.inputs_modified {
+                    return Err(FluxError::Args(ArgError::FlagOptionParseError(
+                        "--validate must come before any inputs or operations".to_owned(),
+                    )));
+                }
+
+                let report = validate_pipeline(&self.args_handler.fork());
+                let json = to_string(&report).context("Failed to serialize validation report")?;
+                println!("{json}");
+
+                if !report.valid {
+                    return Err(FluxError::ValidationFailed(report.problems.len()));
+                }
+
+                self.previous_action = Some(StepAction::Validated);
+            },
+            ArgType::OperationHelp(name) => {
+                let operation = find_operation(&name)?;
+                println!("{operation}");
+                self.previous_action = Some(StepAction::PrintOperations);
+            },
         }
 
-        Ok(self.previous_action.clone().unwrap())
+        let action = self.previous_action.clone().unwrap();
+        self.inputs_modified |= action.modifies_inputs();
+        Ok(action)
+    }
+
+    /// Gets the format for an output. An explicit --format takes priority over the file extension,
+    /// and if neither is recognised we pick a format automatically.\
+    /// An explicit format only applies to one output.
+    fn take_output_format(&mut self, path: &str) -> Option<Type> {
+        self.output_format.take().or_else(|| {
+            Path::new(path)
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(Type::from_extension)
+        })
     }
 
     /// Reads an input file via filename or stdin.
@@ -127,4 +333,16 @@ impl Flux {
             Ok(read(path)?)
         }
     }
+
+    /// Writes an output to a file or stdout.
+    fn write_output(&self, path: &str, data: &[u8]) -> Result<(), FluxError> {
+        if path == "STDOUT" {
+            let mut out = stdout().lock();
+            out.write_all(data)?;
+            out.flush()?;
+            Ok(())
+        } else {
+            Ok(write(path, data)?)
+        }
+    }
 }
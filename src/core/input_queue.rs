@@ -1,28 +1,131 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 use crate::processing::media_object::MediaObject;
 
 /// The input queue. Inputs can be of several types depending on the source. This can help
-/// unnecessary re-encodes when chaining operations.
-pub struct InputQueue(Rc<RefCell<Vec<MediaObject>>>);
+/// unnecessary re-encodes when chaining operations.\
+/// Inputs can also be stored under a name, outside of the queue, to be loaded back into it later.\
+/// Inputs are reference counted so that duplicating them is cheap. An input is only deep-copied if
+/// it is taken out of the queue while another copy of it still exists.
+pub struct InputQueue(
+    Rc<RefCell<Vec<Rc<MediaObject>>>>,
+    Rc<RefCell<HashMap<String, Rc<MediaObject>>>>,
+);
 impl InputQueue {
     pub fn new() -> Self {
-        Self(Rc::new(RefCell::new(vec![])))
+        Self(Rc::new(RefCell::new(vec![])), Rc::new(RefCell::new(HashMap::new())))
     }
 
     pub fn push(&self, input: MediaObject) {
-        self.0.borrow_mut().push(input);
+        self.0.borrow_mut().push(Rc::new(input));
     }
 
     pub fn unshift(&self) -> Option<MediaObject> {
         if self.len() > 0 {
-            Some(self.0.borrow_mut().remove(0))
+            Some(Rc::unwrap_or_clone(self.0.borrow_mut().remove(0)))
         } else {
             None
         }
     }
 
+    /// Returns a copy of the next input without removing it from the queue.
+    pub fn peek(&self) -> Option<MediaObject> {
+        self.0.borrow().first().map(|input| input.as_ref().clone())
+    }
+
+    /// Applies `f` to each of the first `count` inputs, without removing them from the queue.
+    pub fn map_front<T>(&self, count: usize, f: impl Fn(&MediaObject) -> T) -> Vec<T> {
+        self.0.borrow().iter().take(count).map(|input| f(input)).collect()
+    }
+
+    /// Removes the input at `index`.
+    pub fn remove(&self, index: usize) -> Option<MediaObject> {
+        if index < self.len() {
+            Some(Rc::unwrap_or_clone(self.0.borrow_mut().remove(index)))
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a copy of the input at `index` directly before it.
+    pub fn duplicate(&self, index: usize) -> bool {
+        let mut queue = self.0.borrow_mut();
+        if let Some(input) = queue.get(index).cloned() {
+            queue.insert(index, input);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn swap(&self, a: usize, b: usize) -> bool {
+        let mut queue = self.0.borrow_mut();
+        if a < queue.len() && b < queue.len() {
+            queue.swap(a, b);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the first `n` inputs to the back of the queue, or the last `-n` inputs to the front if
+    /// `n` is negative.
+    pub fn rotate(&self, n: i64) {
+        let mut queue = self.0.borrow_mut();
+        if !queue.is_empty() {
+            let n = n.rem_euclid(queue.len() as i64) as usize;
+            queue.rotate_left(n);
+        }
+    }
+
+    /// Moves the input at `index` to the front of the queue.
+    pub fn move_to_front(&self, index: usize) -> bool {
+        let mut queue = self.0.borrow_mut();
+        if index < queue.len() {
+            let input = queue.remove(index);
+            queue.insert(0, input);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the last input in the queue to a named slot, replacing anything previously stored
+    /// under that name.
+    pub fn store_last(&self, name: String) -> bool {
+        if let Some(input) = self.0.borrow_mut().pop() {
+            self.1.borrow_mut().insert(name, input);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Pushes a copy of the input stored under a name to the back of the queue.
+    pub fn load(&self, name: &str) -> bool {
+        if let Some(input) = self.1.borrow().get(name).cloned() {
+            self.0.borrow_mut().push(input);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts a copy of the input stored under a name at `index`, or at the back of the queue if
+    /// `index` is past the end.
+    pub fn insert_named(&self, index: usize, name: &str) -> bool {
+        if let Some(input) = self.1.borrow().get(name).cloned() {
+            let mut queue = self.0.borrow_mut();
+            let index = index.min(queue.len());
+            queue.insert(index, input);
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.0.borrow().len()
     }
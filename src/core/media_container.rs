@@ -1,11 +1,19 @@
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use tracing::debug;
 
-use crate::core::args::ArgsHandler;
 use crate::core::error::{ArgError, FluxError};
 use crate::core::input_queue::InputQueue;
+use crate::operations::registry::find_operation;
+use crate::processing::filetype::Type;
 use crate::processing::media_object::MediaObject;
+use crate::util::{cancel, catch_panic};
+use crate::util::profile::{self, MediaStats};
+use crate::util::progress::{self, ProgressEvent};
 
 #[derive(Default, Clone)]
 pub struct DecodeLimits {
@@ -13,7 +21,34 @@ pub struct DecodeLimits {
     pub frame_rate_limit: Option<u64>,
     pub video_time_limit: Option<Duration>,
     pub resolution_limit: Option<(u64, u64)>,
+    /// Maximum number of pixels decoded from one input, across every frame (width × height ×
+    /// frames). This is checked before frames are decoded.
+    pub pixel_limit: Option<u64>,
     pub video_decode_permitted: bool,
+    /// Density SVGs are rendered at, in dots per inch. Defaults to 72, i.e. the SVG's own size.
+    pub svg_density: Option<f64>,
+    /// Size SVGs are rendered to fit within, whatever their own size. Overrides the density.
+    pub svg_size: Option<(u64, u64)>,
+    /// Message shown when a video input is rejected because video decoding is not permitted.
+    pub video_decode_disabled_message: Option<String>,
+}
+impl DecodeLimits {
+    /// Checks that decoding `frames` frames of `width` x `height` would not exceed the pixel limit.
+    pub fn check_pixel_limit(&self, width: u64, height: u64, frames: u64) -> Result<(), FluxError> {
+        if let Some(limit) = self.pixel_limit
+            && width.saturating_mul(height).saturating_mul(frames) > limit
+        {
+            return Err(FluxError::PixelLimitExceeded(limit));
+        }
+
+        Ok(())
+    }
+
+    pub fn video_decode_disabled_message(&self) -> String {
+        self.video_decode_disabled_message
+            .clone()
+            .unwrap_or("Video decoding is disabled.".to_owned())
+    }
 }
 
 /// Main media container for Flux. Contains everything needed to process a range of input formats by
@@ -23,10 +58,18 @@ pub struct DecodeLimits {
 /// certain data to be present (i.e., audio, or multiple frames).\
 /// When an operation is completed, the result is pushed to the end of the input queue. \
 /// **NOTE: This means that if there are excess inputs, those excess inputs will be consumed BEFORE
-/// the result of the previous operation!**
+/// the result of the previous operation!** The queue operations (`dup`, `swap`, `drop`, `roll` and
+/// `select`) can be used to reorder inputs when this is not desired.
 pub struct MediaContainer {
-    input_queue: InputQueue,
+    pub(crate) input_queue: InputQueue,
     pub limits: DecodeLimits,
+    /// Maximum time each operation may take.
+    pub operation_timeout: Option<Duration>,
+    /// Quality (1-100) of lossy outputs. WebP outputs are lossless unless this is set.
+    pub output_quality: Option<u8>,
+    /// Source of randomness for operations that use it (e.g. `spread` and `scramble`). This is
+    /// randomly seeded unless a seed is set with `set_seed`.
+    rng: RefCell<StdRng>,
 }
 impl MediaContainer {
     pub fn new() -> Self {
@@ -36,9 +79,28 @@ impl MediaContainer {
                 video_decode_permitted: true,
                 ..Default::default()
             },
+            operation_timeout: None,
+            output_quality: None,
+            rng: RefCell::new(StdRng::from_entropy()),
         }
     }
 
+    /// Seeds the random number generator used by operations, so that the same pipeline produces
+    /// the same output.
+    pub fn set_seed(&self, seed: u64) {
+        *self.rng.borrow_mut() = StdRng::seed_from_u64(seed);
+    }
+
+    pub(crate) fn rng(&self) -> RefMut<'_, StdRng> {
+        self.rng.borrow_mut()
+    }
+
+    /// A seed drawn from the random number generator, for work that needs its own generator, such
+    /// as frames processed in parallel.
+    pub(crate) fn next_seed(&self) -> u64 {
+        self.rng.borrow_mut().r#gen()
+    }
+
     pub fn push_input(&self, input: MediaObject) {
         self.input_queue.push(input)
     }
@@ -49,29 +111,130 @@ impl MediaContainer {
             .ok_or(FluxError::Args(ArgError::ArgsExhausted))
     }
 
+    /// Moves the most recent input (typically the result of the last operation) out of the input
+    /// queue and stores it under a name.
+    pub fn store_as(&self, name: String) -> Result<(), FluxError> {
+        if !self.input_queue.store_last(name) {
+            return Err(FluxError::Args(ArgError::ArgsExhausted));
+        }
+
+        Ok(())
+    }
+
+    /// Pushes a copy of a named input to the input queue.
+    pub fn load(&self, name: &str) -> Result<(), FluxError> {
+        if !self.input_queue.load(name) {
+            return Err(FluxError::ParameterError(format!("No input or result named {name}")));
+        }
+
+        Ok(())
+    }
+
     /// Handles a new operation. When the operation (successfully) completes, the result of the
     /// operation will be pushed to the input queue.\
+    /// Options named after one of the operation's inputs (e.g. `base` and `top` for `overlay`)
+    /// reference a named input, which is used in place of that input instead of popping the queue.\
     /// To get the output of this operation, call `get_output`. This will pop the input queue and
     /// encode the data (if necessary).
-    pub fn handle_operation(&self, operation: String) -> Result<(), FluxError> {
-        let parsed = ArgsHandler::parse_operation_name(&operation)?;
+    pub fn handle_operation(&self, operation: &str, mut options: HashMap<String, String>) -> Result<(), FluxError> {
+        let spec = find_operation(operation)?;
+
+        let mut references = vec![];
+        for (i, input_name) in spec.input_names.iter().enumerate() {
+            if let Some(reference) = options.remove(*input_name) {
+                references.push((i, reference));
+            }
+        }
+
+        spec.validate_options(&options)?;
+        spec.check_available()?;
+
+        // inserting in order means each referenced input ends up at its own position, with
+        // unreferenced inputs still taken from the front of the queue
+        for (i, reference) in references {
+            debug!("Using {reference} as input {i} of {operation}");
+            if !self.input_queue.insert_named(i, &reference) {
+                return Err(FluxError::ParameterError(format!(
+                    "No input or result named {reference}"
+                )));
+            }
+        }
+
+        if spec.queue {
+            debug!("Performing queue operation {operation}");
+            return self.perform_queue_operation(spec.name, options);
+        }
+
         debug!("Performing operation {operation}");
         let start = Instant::now();
+        let marker = profile::start_operation(|| self.input_queue.map_front(spec.inputs, MediaStats::of));
+        progress::emit(ProgressEvent::OperationStarted { operation: spec.name });
 
-        let result = self.perform_operation(&parsed.0, parsed.1)?;
+        cancel::check()?;
+        let result = cancel::with_timeout(self.operation_timeout, || {
+            let result = catch_panic(&format!("Operation {operation}"), || {
+                self.perform_operation(spec.name, options)
+            })?;
+            // work may have been skipped or abandoned if the deadline passed
+            cancel::check()?;
+            Ok::<_, FluxError>(result)
+        })?;
 
         debug!("Operation {operation}: took {:?}", start.elapsed());
+        profile::finish_operation(marker, spec.name, MediaStats::of(&result));
+        progress::emit(ProgressEvent::OperationFinished {
+            operation: spec.name,
+            ms: start.elapsed().as_secs_f64() * 1000.0,
+        });
         self.push_input(result);
         Ok(())
     }
 
-    pub fn encode_next(&self) -> Result<Vec<u8>, FluxError> {
+    /// Pops and encodes the next item in the queue. If `format` is `None`, the output format is
+    /// chosen automatically.
+    pub fn encode_next(&self, format: Option<Type>) -> Result<Vec<u8>, FluxError> {
         let next_image = self.pop_input()?;
+        Self::encode(next_image, format, self.output_quality, &self.limits)
+    }
+
+    /// Encodes a copy of the next item in the queue, leaving it in the queue to be used again.
+    pub fn encode_peek(&self, format: Option<Type>) -> Result<Vec<u8>, FluxError> {
+        let next_image = self
+            .input_queue
+            .peek()
+            .ok_or(FluxError::Args(ArgError::ArgsExhausted))?;
+        Self::encode(next_image, format, self.output_quality, &self.limits)
+    }
+
+    fn encode(
+        image: MediaObject,
+        format: Option<Type>,
+        quality: Option<u8>,
+        limits: &DecodeLimits,
+    ) -> Result<Vec<u8>, FluxError> {
+        let start = Instant::now();
+        let stats = MediaStats::of(&image);
+        progress::emit(ProgressEvent::EncodeStarted);
 
+        let encoded = catch_panic("Encoding", || image.encode_as(format, quality, limits))?;
+        // work done while encoding skips frames once the deadline passes, so the output may be
+        // incomplete
+        cancel::check()?;
+
+        profile::record_encode(start.elapsed(), &encoded, stats);
+        progress::emit(ProgressEvent::EncodeFinished {
+            ms: start.elapsed().as_secs_f64() * 1000.0,
+        });
+
+        Ok(encoded)
+    }
+
+    /// Checks that every item in the queue has been output.
+    pub fn finish(&self) -> Result<(), FluxError> {
         if self.input_queue.len() > 0 {
             return Err(FluxError::ResidualImages(self.input_queue.len() as u64));
         }
 
-        next_image.encode(&self.limits)
+        Ok(())
     }
 }
@@ -1,5 +1,10 @@
 pub mod args;
+pub mod batch;
+pub mod doctor;
 pub mod error;
 pub mod flux;
 pub mod input_queue;
 pub mod media_container;
+pub mod script;
+pub mod server;
+pub mod validate;
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use super::args::ArgType;
+use super::error::ArgError;
+use crate::processing::filetype::Type;
+
+/// Parses a pipeline script into the same argument types as the command line. Each non-blank line
+/// that is not a `#` comment is one of:
+/// - `input <path> [as <name>]`
+/// - `op <operation> [key=value ...] [as <name>]`
+/// - `load <name>`
+/// - `output <path> [format=<format>]`
+/// - `peek <path> [format=<format>]`, which outputs the next item without removing it from the
+///   input queue
+pub fn parse_script(src: &str) -> Result<Vec<ArgType>, ArgError> {
+    let mut args = vec![];
+
+    for (i, line) in src.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let err = |message: String| ArgError::ScriptParseError(i + 1, message);
+
+        let mut tokens = tokenize(line).map_err(err)?;
+        let command = tokens.remove(0);
+        let name = take_name(&mut tokens).map_err(err)?;
+
+        match &command[..] {
+            "input" => {
+                let [path] = &tokens[..] else {
+                    return Err(err("expected input <path> [as <name>]".to_owned()));
+                };
+                args.push(ArgType::InputPath(path.clone()));
+            },
+            "op" => {
+                if tokens.is_empty() {
+                    return Err(err("expected op <operation> [key=value ...] [as <name>]".to_owned()));
+                }
+
+                let operation = tokens.remove(0);
+                let mut options = HashMap::new();
+                for option in tokens {
+                    let (key, value) = option
+                        .split_once('=')
+                        .ok_or(err(format!("option \"{option}\" has a key, but no value")))?;
+
+                    if key.is_empty() {
+                        return Err(err("option key cannot be blank".to_owned()));
+                    } else if value.is_empty() {
+                        return Err(err(format!("option \"{option}\" has a key, but no value")));
+                    }
+
+                    options.insert(key.to_owned(), value.to_owned());
+                }
+                args.push(ArgType::Operation(operation, options));
+            },
+            "load" => {
+                let [loaded] = &tokens[..] else {
+                    return Err(err("expected load <name>".to_owned()));
+                };
+                if name.is_some() {
+                    return Err(err("load cannot be used with as".to_owned()));
+                }
+                args.push(ArgType::Load(loaded.clone()));
+            },
+            "output" | "peek" => {
+                let (path, format) = match &tokens[..] {
+                    [path] => (path, None),
+                    [path, format] if format.starts_with("format=") => (path, Some(&format["format=".len()..])),
+                    _ => return Err(err(format!("expected {command} <path> [format=<format>]"))),
+                };
+                if name.is_some() {
+                    return Err(err(format!("{command} cannot be used with as")));
+                }
+
+                if let Some(format) = format {
+                    let ty = Type::from_extension(format).ok_or(err(format!("unrecognised output format {format}")))?;
+                    args.push(ArgType::OutputFormat(ty));
+                }
+                if command == "peek" {
+                    args.push(ArgType::PeekOutputPath(path.clone()));
+                } else {
+                    args.push(ArgType::OutputPath(path.clone()));
+                }
+            },
+            _ => return Err(err(format!("unrecognised command {command}"))),
+        }
+
+        if let Some(name) = name {
+            args.push(ArgType::StoreAs(name));
+        }
+    }
+
+    Ok(args)
+}
+
+/// Removes a trailing `as <name>` from the tokens of a line, if present.
+fn take_name(tokens: &mut Vec<String>) -> Result<Option<String>, String> {
+    if tokens.len() >= 2 && tokens[tokens.len() - 2] == "as" {
+        let name = tokens.pop();
+        tokens.pop();
+        Ok(name)
+    } else if tokens.last().is_some_and(|t| t == "as") {
+        Err("expected a name after as".to_owned())
+    } else {
+        Ok(None)
+    }
+}
+
+/// Splits a line on whitespace, keeping whitespace inside of double quotes.
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut in_quotes = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                in_token = true;
+            },
+            '\\' if in_quotes => match chars.next() {
+                Some(e @ ('"' | '\\')) => current.push(e),
+                Some(e) => {
+                    current.push('\\');
+                    current.push(e);
+                },
+                None => current.push('\\'),
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            },
+            c => {
+                current.push(c);
+                in_token = true;
+            },
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quote".to_owned());
+    }
+
+    if in_token {
+        tokens.push(current);
+    }
+
+    Ok(tokens)
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::fs::{read, remove_file, write};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, error};
+
+use super::error::{ArgError, ErrorReport, FluxError};
+use super::media_container::{DecodeLimits, MediaContainer};
+use crate::processing::filetype::Type;
+use crate::processing::media_object::MediaObject;
+use crate::util::cancel;
+
+/// Options for `flux serve`.
+pub struct ServeOptions {
+    pub socket: String,
+}
+impl ServeOptions {
+    /// Parses the arguments following `serve`.
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, ArgError> {
+        let mut socket = None;
+
+        while let Some(arg) = args.next() {
+            match &arg[..] {
+                "--socket" => socket = Some(args.next().ok_or(ArgError::ArgsExhausted)?),
+                // handled before parsing, see `ErrorFormat::from_args`
+                "--error-format" => {
+                    args.next().ok_or(ArgError::ArgsExhausted)?;
+                },
+                _ => return Err(ArgError::UnrecognisedFlag(arg)),
+            }
+        }
+
+        Ok(Self {
+            socket: socket.ok_or(ArgError::FlagOptionParseError(
+                "serve requires --socket [path]".to_owned(),
+            ))?,
+        })
+    }
+}
+
+/// A job sent to the server. Each job is one line of JSON, for example:
+/// `{"id":1,"inputs":[{"path":"in.gif"}],"operations":[{"name":"ghost","options":{"depth":"5"}}],"format":"mp4"}`
+#[derive(Deserialize)]
+pub struct Job {
+    /// Echoed back in the response, so that responses can be matched to jobs.
+    #[serde(default)]
+    pub id: Value,
+    pub inputs: Vec<JobInput>,
+    #[serde(default)]
+    pub operations: Vec<JobOperation>,
+    #[serde(default)]
+    pub limits: JobLimits,
+    /// Output format, as a file extension. Chosen automatically if omitted.
+    pub format: Option<String>,
+    /// Path to write the output to. If omitted, the output is returned in the response.
+    pub output: Option<String>,
+    /// Maximum time the job may take, in seconds.
+    pub timeout: Option<f64>,
+    /// Maximum time each operation may take, in seconds.
+    pub operation_timeout: Option<f64>,
+    /// Seed for operations that use randomness. Randomly seeded if omitted.
+    pub seed: Option<u64>,
+    /// Quality of lossy outputs, from 1 to 100.
+    pub quality: Option<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobInput {
+    Path(String),
+    /// Base64-encoded input data.
+    Data(String),
+}
+
+#[derive(Deserialize)]
+pub struct JobOperation {
+    pub name: String,
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+/// Per-job equivalent of the decode limit flags.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct JobLimits {
+    pub frame_limit: Option<u64>,
+    pub frame_rate_limit: Option<u64>,
+    /// Maximum video length, in seconds.
+    pub video_time_limit: Option<f64>,
+    pub resolution_limit: Option<(u64, u64)>,
+    pub pixel_limit: Option<u64>,
+    /// Density SVGs are rendered at, in dots per inch.
+    pub svg_density: Option<f64>,
+    pub svg_size: Option<(u64, u64)>,
+    pub disable_video_decode: bool,
+    pub video_decode_disabled_message: Option<String>,
+}
+impl JobLimits {
+    fn to_decode_limits(&self) -> Result<DecodeLimits, FluxError> {
+        let video_time_limit = self
+            .video_time_limit
+            .map(|secs| secs_to_duration(secs, "video time limit"))
+            .transpose()?;
+        if let Some(density) = self.svg_density
+            && !(density.is_finite() && density > 0.0)
+        {
+            return Err(FluxError::ParameterError(format!("Invalid SVG density {density}")));
+        }
+
+        Ok(DecodeLimits {
+            frame_limit: self.frame_limit,
+            frame_rate_limit: self.frame_rate_limit,
+            video_time_limit,
+            resolution_limit: self.resolution_limit,
+            pixel_limit: self.pixel_limit,
+            svg_density: self.svg_density,
+            svg_size: self.svg_size,
+            video_decode_permitted: !self.disable_video_decode,
+            video_decode_disabled_message: self.video_decode_disabled_message.clone(),
+        })
+    }
+}
+
+fn secs_to_duration(secs: f64, name: &str) -> Result<Duration, FluxError> {
+    Duration::try_from_secs_f64(secs).map_err(|e| FluxError::ParameterError(format!("Invalid {name} {secs}: {e}")))
+}
+
+#[derive(Serialize)]
+struct JobResponse {
+    id: Value,
+    ok: bool,
+    /// Base64-encoded output, if no output path was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorReport>,
+}
+
+/// Listens on a Unix socket and runs jobs until the process is terminated. Each connection is
+/// handled on its own thread, so jobs sent over separate connections run concurrently. Jobs sent
+/// over the same connection are run in order, and one response line is written for each.
+pub fn serve(options: &ServeOptions) -> Result<(), FluxError> {
+    // a socket left behind by a previous instance would stop us from binding
+    if let Ok(metadata) = Path::new(&options.socket).metadata()
+        && metadata.file_type().is_socket()
+    {
+        let _ = remove_file(&options.socket);
+    }
+
+    let listener = UnixListener::bind(&options.socket)
+        .map_err(|e| FluxError::Other(format!("Failed to bind socket {}: {e}", options.socket)))?;
+
+    debug!("Listening on {}", options.socket);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream) {
+                        error!("Connection error: {e}");
+                    }
+                });
+            },
+            Err(e) => error!("Failed to accept connection: {e}"),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(stream: UnixStream) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Job>(&line) {
+            Ok(job) => {
+                let id = job.id.clone();
+                match run_job(job) {
+                    Ok(data) => JobResponse {
+                        id,
+                        ok: true,
+                        data,
+                        error: None,
+                    },
+                    Err(e) => JobResponse {
+                        id,
+                        ok: false,
+                        data: None,
+                        error: Some(ErrorReport::from(&e)),
+                    },
+                }
+            },
+            Err(e) => JobResponse {
+                id: Value::Null,
+                ok: false,
+                data: None,
+                error: Some(ErrorReport::from(&FluxError::ParameterError(format!("Invalid job: {e}")))),
+            },
+        };
+
+        let mut json = serde_json::to_vec(&response)?;
+        json.push(b'\n');
+        writer.write_all(&json)?;
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Runs a job through its own `MediaContainer`, returning the base64-encoded output if the job
+/// has no output path.
+pub fn run_job(job: Job) -> Result<Option<String>, FluxError> {
+    let timeout = job.timeout.map(|t| secs_to_duration(t, "timeout")).transpose()?;
+    cancel::with_timeout(timeout, || run_job_inner(job))
+}
+
+fn run_job_inner(job: Job) -> Result<Option<String>, FluxError> {
+    let mut container = MediaContainer::new();
+    container.limits = job.limits.to_decode_limits()?;
+    container.operation_timeout = job
+        .operation_timeout
+        .map(|t| secs_to_duration(t, "operation timeout"))
+        .transpose()?;
+    if let Some(seed) = job.seed {
+        container.set_seed(seed);
+    }
+    if let Some(quality) = job.quality
+        && !(1..=100).contains(&quality)
+    {
+        return Err(FluxError::ParameterError(format!(
+            "Invalid quality {quality}, expected a number from 1 to 100"
+        )));
+    }
+    container.output_quality = job.quality;
+
+    for input in job.inputs {
+        let data = match input {
+            JobInput::Path(path) => read(&path)?,
+            JobInput::Data(data) => BASE64
+                .decode(data)
+                .map_err(|e| FluxError::ParameterError(format!("Invalid base64 input: {e}")))?,
+        };
+        container.push_input(MediaObject::Encoded(data));
+    }
+
+    for operation in job.operations {
+        container.handle_operation(&operation.name, operation.options)?;
+    }
+
+    // as on the command line, fall back to the output extension if no format is given
+    let format = job
+        .format
+        .map(|f| Type::from_extension(&f).ok_or(FluxError::UnsupportedOutputFormat(f)))
+        .transpose()?
+        .or_else(|| {
+            job.output
+                .as_ref()
+                .and_then(|p| Path::new(p).extension())
+                .and_then(|e| e.to_str())
+                .and_then(Type::from_extension)
+        });
+
+    let output = container.encode_next(format)?;
+    container.finish()?;
+
+    if let Some(path) = job.output {
+        write(path, output)?;
+        Ok(None)
+    } else {
+        Ok(Some(BASE64.encode(output)))
+    }
+}
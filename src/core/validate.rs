@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::args::{ArgType, ArgsHandler};
+use super::error::{ArgError, ErrorReport, FluxError};
+use crate::operations::registry::find_operation;
+
+/// A problem found while validating a pipeline, along with the step (starting from 1) it was found
+/// at.
+#[derive(Serialize, Debug)]
+pub struct ValidationProblem {
+    pub step: usize,
+    #[serde(flatten)]
+    pub error: ErrorReport,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub problems: Vec<ValidationProblem>,
+}
+
+/// Tracks the input queue and named inputs as the pipeline would, without reading any inputs or
+/// running any operations.
+struct Simulation {
+    step: usize,
+    queue: usize,
+    names: HashSet<String>,
+    outputs: usize,
+    problems: Vec<ValidationProblem>,
+}
+impl Simulation {
+    fn problem(&mut self, error: FluxError) {
+        self.problems.push(ValidationProblem {
+            step: self.step,
+            error: ErrorReport::from(&error),
+        });
+    }
+
+    /// Pops `count` items from the queue, reporting a problem if there are not enough.
+    fn pop(&mut self, count: usize, what: &str) {
+        if self.queue < count {
+            self.problem(FluxError::ParameterError(format!(
+                "{what} needs {count} input(s), but only {} would be in the queue",
+                self.queue
+            )));
+        }
+
+        self.queue = self.queue.saturating_sub(count);
+    }
+
+    fn operation(&mut self, name: &str, mut options: HashMap<String, String>) {
+        let spec = match find_operation(name) {
+            Ok(spec) => spec,
+            Err(e) => return self.problem(e),
+        };
+
+        // named references are used in place of popping the queue, see
+        // `MediaContainer::handle_operation`
+        let mut referenced = 0;
+        for input_name in spec.input_names {
+            if let Some(reference) = options.remove(*input_name) {
+                referenced += 1;
+                if !self.names.contains(&reference) {
+                    self.problem(FluxError::ParameterError(format!(
+                        "No input or result named {reference}"
+                    )));
+                }
+            }
+        }
+
+        for problem in spec.option_problems(&options) {
+            self.problem(problem);
+        }
+
+        if let Err(e) = spec.check_available() {
+            self.problem(e);
+        }
+
+        if spec.queue {
+            let what = format!("Operation {}", spec.name);
+            match spec.name {
+                "dup" => {
+                    self.pop(1, &what);
+                    self.queue += 2;
+                },
+                "drop" => self.pop(1, &what),
+                "select" => {
+                    let index = options.get("index").and_then(|i| i.parse::<usize>().ok()).unwrap_or(0);
+                    // selecting reorders the queue without changing its length
+                    let queue = self.queue;
+                    self.pop(index.saturating_add(1), &what);
+                    self.queue = queue;
+                },
+                _ => {
+                    self.pop(spec.inputs, &what);
+                    self.queue += spec.inputs;
+                },
+            }
+
+            return;
+        }
+
+        // heart-locket generates its second image when given text
+        let inputs = if spec.name == "heart-locket" && options.contains_key("text") {
+            1
+        } else {
+            spec.inputs
+        };
+
+        self.pop(inputs.saturating_sub(referenced), &format!("Operation {}", spec.name));
+        self.queue += 1;
+    }
+}
+
+/// Parses every remaining argument and checks that the pipeline would run, reporting every problem
+/// found instead of stopping at the first. This checks operation names and options, that each
+/// operation would have enough inputs, that named inputs exist, and that every item in the queue
+/// is output. Inputs are not read, although input paths are checked to exist.
+pub fn validate_pipeline(args: &ArgsHandler) -> ValidationReport {
+    let mut sim = Simulation {
+        step: 0,
+        queue: 0,
+        names: HashSet::new(),
+        outputs: 0,
+        problems: vec![],
+    };
+
+    while !args.is_exhausted() {
+        sim.step += 1;
+
+        let arg = match args.parse_next() {
+            Ok(arg) => arg,
+            Err(ArgError::ArgsExhausted) => {
+                sim.problem(FluxError::Args(ArgError::ArgsExhausted));
+                break;
+            },
+            Err(e) => {
+                sim.problem(FluxError::Args(e));
+                continue;
+            },
+        };
+
+        match arg {
+            ArgType::InputPath(path) => {
+                if path != "STDIN" && !Path::new(&path).is_file() {
+                    sim.problem(FluxError::ParameterError(format!("Input {path} does not exist")));
+                }
+                sim.queue += 1;
+            },
+            ArgType::Operation(name, options) => sim.operation(&name, options),
+            ArgType::StoreAs(name) => {
+                sim.pop(1, &format!("Storing as {name}"));
+                sim.names.insert(name);
+            },
+            ArgType::Load(name) => {
+                if !sim.names.contains(&name) {
+                    sim.problem(FluxError::ParameterError(format!("No input or result named {name}")));
+                }
+                sim.queue += 1;
+            },
+            ArgType::OutputPath(_) => {
+                sim.pop(1, "Output");
+                sim.outputs += 1;
+            },
+            ArgType::PeekOutputPath(_) => {
+                sim.pop(1, "Output");
+                sim.queue += 1;
+                sim.outputs += 1;
+            },
+            ArgType::Info => {
+                sim.pop(1, "Info");
+                sim.outputs += 1;
+            },
+            ArgType::Version | ArgType::ListOperations | ArgType::Doctor | ArgType::OperationHelp(_) => {
+                sim.outputs += 1;
+            },
+            _ => {},
+        }
+    }
+
+    if sim.outputs == 0 {
+        sim.problem(FluxError::ParameterError("The pipeline has no output".to_owned()));
+    } else if sim.queue > 0 {
+        sim.problem(FluxError::ResidualImages(sim.queue as u64));
+    }
+
+    ValidationReport {
+        valid: sim.problems.is_empty(),
+        problems: sim.problems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(args: &[&str]) -> ValidationReport {
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        validate_pipeline(&ArgsHandler::without_program_name(args))
+    }
+
+    #[test]
+    fn select_out_of_range_is_a_problem() {
+        let report = validate(&["-i", "STDIN", "-o", "select[index=18446744073709551615]", "out.png"]);
+        assert!(!report.valid);
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(report.problems[0].step, 2);
+    }
+
+    #[test]
+    fn select_keeps_queue_length() {
+        let report = validate(&["-i", "STDIN", "-i", "STDIN", "-o", "select[index=1]", "a.png", "b.png"]);
+        assert!(report.valid);
+    }
+}
@@ -0,0 +1,53 @@
+#![allow(internal_features)]
+#![feature(
+    decl_macro,
+    core_intrinsics,
+    try_trait_v2,
+    compiler_builtins,
+    portable_simd,
+    link_llvm_intrinsics,
+    simd_ffi
+)]
+
+//! Flux as a library. Operations can be run in-process by pushing inputs to a [`MediaContainer`],
+//! calling [`MediaContainer::handle_operation`] and encoding the result with
+//! [`MediaContainer::encode_next`]:
+//!
+//! ```no_run
+//! use std::collections::HashMap;
+//!
+//! use flux::{MediaContainer, MediaObject};
+//!
+//! let container = MediaContainer::new();
+//! container.push_input(MediaObject::Encoded(std::fs::read("input.gif").unwrap()));
+//! container.handle_operation("invert", HashMap::new()).unwrap();
+//! let output = container.encode_next(None).unwrap();
+//! ```
+//!
+//! [`Flux`] runs a whole pipeline from command line style arguments, as the `flux` binary does.
+
+use std::sync::OnceLock;
+
+pub mod core;
+pub mod operations;
+pub mod processing;
+pub mod util;
+pub mod vips;
+
+pub use crate::core::error::{ArgError, FluxError};
+pub use crate::core::flux::{Flux, StepAction};
+pub use crate::core::media_container::{DecodeLimits, MediaContainer};
+pub use crate::operations::registry::{
+    find_operation, get_operation, OperationSpec, OptionSpec, OptionType, OPERATIONS,
+};
+pub use crate::processing::filetype::Type;
+pub use crate::processing::media_object::MediaObject;
+
+static VIPS_INIT: OnceLock<bool> = OnceLock::new();
+
+/// Initialises libvips, returning whether it succeeded. This only does any work the first time it
+/// is called, and is called automatically when needed, so it does not need to be called before
+/// using the library.
+pub fn init() -> bool {
+    *VIPS_INIT.get_or_init(|| unsafe { vips::ffi::v_vips_init() == 0 })
+}
@@ -1,31 +1,22 @@
-#![allow(internal_features)]
-#![feature(
-    decl_macro,
-    core_intrinsics,
-    try_trait_v2,
-    exitcode_exit_method,
-    compiler_builtins,
-    portable_simd,
-    link_llvm_intrinsics,
-    simd_ffi
-)]
-
-use core::flux::{Flux, StepAction};
-use std::process::{Command, ExitCode};
+#![feature(exitcode_exit_method)]
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 use std::{fs, thread};
 
+use flux::core::error::{ErrorFormat, ErrorReport};
+use flux::{Flux, FluxError};
+use flux::core::batch::{run_batch, BatchOptions};
+use flux::core::server::{serve, ServeOptions};
+use flux::util::{cancel, kill_children_and_remove_tmp_files, profile};
 use signal_hook::consts::SIGTERM;
 use signal_hook::iterator::Signals;
 use time::format_description;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt::time::UtcTime;
-use vips::ffi::v_vips_init;
 
-pub mod core;
-pub mod operations;
-pub mod processing;
-pub mod util;
-pub mod vips;
+/// How long to wait for an operation to stop after the timeout passes before exiting anyway.
+const TIMEOUT_GRACE_PERIOD: Duration = Duration::from_secs(2);
 
 fn main() -> ExitCode {
     let filter = EnvFilter::from_default_env();
@@ -36,47 +27,37 @@ fn main() -> ExitCode {
         .with_timer(UtcTime::new(format_description::parse(description).unwrap()))
         .with_line_number(true)
         .with_env_filter(filter)
+        // stdout may be used for the output image
+        .with_writer(std::io::stderr)
         .init();
 
-    let args = std::env::args();
-    let mut flux = Flux::new(args);
+    flux::init();
 
-    unsafe {
-        v_vips_init();
-    }
+    let args = std::env::args().collect::<Vec<_>>();
+    let error_format = ErrorFormat::from_args(&args);
+
+    let serve_options = if args.get(1).map(String::as_str) == Some("serve") {
+        match ServeOptions::parse(args[2..].iter().cloned()) {
+            Ok(o) => Some(o),
+            Err(e) => return report_error(&FluxError::Args(e), error_format),
+        }
+    } else {
+        None
+    };
 
     // handle SIGTERM for graceful shutdown of child processes (e.g. ffmpeg)
+    let socket = serve_options.as_ref().map(|o| o.socket.clone());
     let mut signals = Signals::new(&[SIGTERM]).unwrap();
     thread::spawn(move || {
         for sig in signals.forever() {
             if sig == SIGTERM {
-                let current_pid = std::process::id();
+                if let Some(socket) = &socket {
+                    let _ = fs::remove_file(socket);
+                }
 
-                let mut cmd = Command::new("pkill");
-                cmd.arg("-P");
-                cmd.arg(&current_pid.to_string());
-                let o = cmd.output();
-                if let Err(e) = o {
+                if let Err(e) = kill_children_and_remove_tmp_files() {
                     eprintln!("flux: graceful shutdown failed: {}", e.to_string());
                     ExitCode::FAILURE.exit_process();
-                };
-
-                // cleanup all temp files
-                let files = fs::read_dir("/tmp")
-                    .map_err(|e| {
-                        eprintln!("flux: graceful shutdown failed: {}", e.to_string());
-                        ExitCode::FAILURE.exit_process();
-                    })
-                    .unwrap();
-
-                for file in files {
-                    if let Ok(f) = file {
-                        let name = f.file_name();
-                        let name = name.to_str().unwrap();
-                        if name.starts_with(&format!("{current_pid}-")) {
-                            let _ = fs::remove_file(format!("/tmp/{name}"));
-                        }
-                    }
                 }
 
                 ExitCode::SUCCESS.exit_process();
@@ -84,19 +65,69 @@ fn main() -> ExitCode {
         }
     });
 
-    loop {
-        let state = flux.step();
+    // operations stop at the deadline when they can, but some work cannot be interrupted, so give
+    // up on it if it runs on well past the deadline
+    thread::spawn(move || {
+        loop {
+            thread::sleep(Duration::from_millis(100));
 
-        match state {
-            Ok(s) => {
-                if s == StepAction::OutputWritten || s == StepAction::MediaInfo || s == StepAction::PrintVersion {
-                    break ExitCode::SUCCESS;
-                }
-            },
-            Err(e) => {
-                eprintln!("{e:#}");
-                break ExitCode::FAILURE;
-            },
+            if let Some(deadline) = cancel::global_deadline()
+                && Instant::now() >= deadline + TIMEOUT_GRACE_PERIOD
+            {
+                let _ = kill_children_and_remove_tmp_files();
+                report_error(&FluxError::Timeout, error_format).exit_process();
+            }
         }
+    });
+
+    if let Some(options) = serve_options {
+        return match serve(&options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => report_error(&e, error_format),
+        };
     }
+
+    if args.get(1).map(String::as_str) == Some("batch") {
+        let options = match BatchOptions::parse(args[2..].to_vec()) {
+            Ok(o) => o,
+            Err(e) => return report_error(&FluxError::Args(e), error_format),
+        };
+
+        let summary = run_batch(&options);
+        println!("{}", serde_json::to_string(&summary).unwrap());
+
+        return if summary.failed == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    let mut flux = Flux::new(args);
+    let result = flux.run();
+
+    // stdout may be used for the output image, so the profile goes to stderr, even on failure
+    if let Some(report) = profile::report()
+        && let Ok(json) = serde_json::to_string(&report)
+    {
+        eprintln!("{json}");
+    }
+
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => report_error(&e, error_format),
+    }
+}
+
+/// Prints an error in the requested format, returning the exit code for its category.
+fn report_error(e: &FluxError, format: ErrorFormat) -> ExitCode {
+    match format {
+        ErrorFormat::Text => eprintln!("{e:#}"),
+        ErrorFormat::Json => match serde_json::to_string(&ErrorReport::from(e)) {
+            Ok(json) => eprintln!("{json}"),
+            Err(_) => eprintln!("{e:#}"),
+        },
+    }
+
+    ExitCode::from(e.category().exit_code())
 }
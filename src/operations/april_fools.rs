@@ -11,7 +11,7 @@ use super::OperationResult;
 impl MediaContainer {
     pub fn april_fools(&self) -> OperationResult {
         let input = self.pop_input()?;
-        let first = if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        let first = if let Some(v) = input.try_encoded_video(&self.limits) {
             DynamicImageWrapper::new(load_from_memory(&get_video_first_frame(v?)?)?, None)
         } else {
             input.to_dynamic_images(&self.limits)?.into_owned().images[0].clone()
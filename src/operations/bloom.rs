@@ -15,14 +15,14 @@ impl MediaContainer {
         let input = self.pop_input()?;
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
 
-        dyn_images.iter_images_mut(|f, _| {
+        dyn_images.iter_images_mut_fallible(|f, _| {
             gegl::softglow(
                 f,
                 options.radius.unwrap_or(5) as usize,
                 options.brightness.unwrap_or(35) as usize,
                 options.sharpness.unwrap_or(85) as usize,
             )
-        });
+        })?;
 
         Ok(MediaObject::DynamicImages(dyn_images))
     }
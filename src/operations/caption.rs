@@ -14,7 +14,7 @@ impl MediaContainer {
     pub fn caption(&self, text: &str, bottom: bool, black: bool) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(input) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(input) = input.try_encoded_video(&self.limits) {
             let input = input?;
             let (w, _) = get_video_dimensions(input)?;
             let mut text = vips_generate_caption(text, w)?;
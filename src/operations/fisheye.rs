@@ -10,7 +10,7 @@ impl MediaContainer {
 
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
 
-        dyn_images.iter_images_mut(|f, _| gegl::fisheye(f));
+        dyn_images.iter_images_mut_fallible(|f, _| gegl::fisheye(f))?;
 
         Ok(MediaObject::DynamicImages(dyn_images))
     }
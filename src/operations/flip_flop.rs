@@ -10,7 +10,7 @@ impl MediaContainer {
     pub fn flip(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             let result = ffmpeg_operations::flip_video(v?)?;
             Ok(MediaObject::Encoded(result))
         } else {
@@ -28,7 +28,7 @@ impl MediaContainer {
     pub fn flop(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             let result = ffmpeg_operations::flop_video(v?)?;
             Ok(MediaObject::Encoded(result))
         } else {
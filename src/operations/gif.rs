@@ -16,7 +16,7 @@ impl MediaContainer {
             && get_sig(e) == Some(Type::Gif)
         {
             return Ok(input);
-        } else if let Some(e) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        } else if let Some(e) = input.try_encoded_video(&self.limits) {
             return ffmpeg_operations::video_to_gif(e?).map(MediaObject::Encoded);
         }
 
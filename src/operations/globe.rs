@@ -25,12 +25,12 @@ impl MediaContainer {
                 .for_each(|i| i.1 = Some(Duration::from_millis(50)));
         }
 
-        dyn_images.iter_images_mut(|f, i| {
+        dyn_images.iter_images_mut_fallible(|f, i| {
             let roll_x = f.width() - ((f.width() / len as u32) * i as u32);
 
             let rolled = roll_image(f, roll_x, 0);
-            DynamicImage::ImageRgba8(globe(&rolled).to_rgba8())
-        });
+            Ok(DynamicImage::ImageRgba8(globe(&rolled)?.to_rgba8()))
+        })?;
 
         Ok(MediaObject::DynamicImages(dyn_images))
     }
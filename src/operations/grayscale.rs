@@ -9,7 +9,7 @@ use super::OperationResult;
 impl MediaContainer {
     pub fn grayscale(&self) -> OperationResult {
         let input = self.pop_input()?;
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return ffmpeg_operations::grayscale_video(v?).map(MediaObject::Encoded);
         };
 
@@ -3,11 +3,12 @@ use image::GenericImageView;
 use serde::Serialize;
 
 use crate::core::error::FluxError;
-use crate::core::media_container::MediaContainer;
+use crate::core::media_container::{DecodeLimits, MediaContainer};
 use crate::processing::ffmpeg::{get_video_dimensions, get_video_fps, get_video_frame_count, get_video_length};
 use crate::processing::filetype::{get_sig, get_sig_incl_mp4, Type};
 use crate::processing::gif::gif_get_comments;
 use crate::processing::media_object::MediaObject;
+use crate::processing::webp::webp_get_frame_count;
 
 #[derive(Serialize)]
 pub struct ImageInfo {
@@ -39,7 +40,13 @@ impl MediaContainer {
     /// Get some metadata about an image or video.
     pub fn info(&self) -> Result<MediaInfo, FluxError> {
         let input = self.pop_input()?;
-        if let Some(v) = input.try_encoded_video(true).map(|v| v.unwrap()) {
+        // info does not decode the video, so it is always permitted
+        let limits = DecodeLimits {
+            video_decode_permitted: true,
+            ..self.limits.clone()
+        };
+
+        if let Some(v) = input.try_encoded_video(&limits).map(|v| v.unwrap()) {
             let file_size_bytes = v.len() as u64;
             let mime_type = get_sig_incl_mp4(v).unwrap().as_mime().to_owned();
             let dimensions = get_video_dimensions(v).map(|(w, h)| format!("{w}x{h}"))?;
@@ -67,17 +74,18 @@ impl MediaContainer {
                     format!("{}x{}", d.0, d.1)
                 })
                 .unwrap_or("0x0".to_owned());
-            let frame_count = if dyn_images.images.len() > 1 {
-                Some(dyn_images.images.len() as u64)
-            } else {
-                None
+            // the frame limit may have stopped decoding early, so count WebP frames from the file
+            let frame_count = match get_sig(e) {
+                Some(Type::Webp) => webp_get_frame_count(e),
+                _ => dyn_images.images.len() as u64,
             };
+            let frame_count = if frame_count > 1 { Some(frame_count) } else { None };
 
             let repeat = if let Repeat::Finite(n) = dyn_images.repeat
-                && dyn_images.images.len() > 1
+                && frame_count.is_some()
             {
                 Some(format!("{n} times"))
-            } else if dyn_images.images.len() > 1 {
+            } else if frame_count.is_some() {
                 Some("Infinite".to_string())
             } else {
                 None
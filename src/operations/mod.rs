@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use bloom::BloomOptions;
+use registry::get_operation;
 use resize::ResizeOptions;
 
 use crate::core::error::FluxError;
@@ -36,7 +37,9 @@ pub mod paint;
 pub mod ping_pong;
 pub mod pixelate;
 pub mod posterize;
+pub mod queue;
 pub mod rainbow;
+pub mod registry;
 pub mod resize;
 pub mod reverse;
 pub mod rotate;
@@ -119,11 +122,31 @@ fn option_get_str<'a>(options: &'a HashMap<String, String>, name: &str) -> Optio
 }
 
 impl MediaContainer {
+    /// Performs an operation that reorders the input queue instead of producing a result.
+    pub fn perform_queue_operation(&self, operation: &str, options: HashMap<String, String>) -> Result<(), FluxError> {
+        match operation {
+            "dup" => self.dup(),
+            "swap" => self.swap(),
+            "drop" => self.drop_input(),
+            "roll" => self.roll(option_get_i64(&options, "n")?),
+            "select" => {
+                let index = option_get_u64(&options, "index")?.ok_or(FluxError::ParameterError(
+                    "Missing required option index for operation select".to_owned(),
+                ))?;
+
+                self.select(index)
+            },
+            _ => Err(FluxError::ParameterError(format!("Unrecognised queue operation {operation}"))),
+        }
+    }
+
     pub fn perform_operation(
         &self,
         operation: &str,
         options: HashMap<String, String>,
     ) -> Result<MediaObject, FluxError> {
+        let operation = get_operation(operation).map(|o| o.name).unwrap_or(operation);
+
         Ok(match operation {
             "ah-shit" => self.ah_shit()?,
             "april-fools" => self.april_fools()?,
@@ -211,7 +234,13 @@ impl MediaContainer {
 
                 self.pixelate(strength)?
             },
-            "posterize" => self.posterize(option_get_str(&options, "cols").unwrap())?,
+            "posterize" => {
+                let cols = option_get_str(&options, "cols").ok_or(FluxError::ParameterError(
+                    "Missing required option cols for operation posterize".to_owned(),
+                ))?;
+
+                self.posterize(cols)?
+            },
             "rainbow" => self.rainbow()?,
             "resize" => {
                 let width = option_get_u64(&options, "width")?;
@@ -280,3 +309,31 @@ impl MediaContainer {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::media_container::MediaContainer;
+    use crate::operations::registry::OPERATIONS;
+
+    #[test]
+    fn every_registered_operation_is_dispatched() {
+        for spec in OPERATIONS {
+            // the queue is empty, so every operation fails, but not because it is unknown
+            let container = MediaContainer::new();
+            let error = if spec.queue {
+                container.perform_queue_operation(spec.name, HashMap::new()).err()
+            } else {
+                container.perform_operation(spec.name, HashMap::new()).err()
+            }
+            .unwrap_or_else(|| panic!("{} succeeded without any inputs", spec.name));
+
+            assert!(
+                !error.to_string().contains("Unrecognised"),
+                "{} has no dispatch arm: {error}",
+                spec.name
+            );
+        }
+    }
+}
@@ -12,7 +12,7 @@ impl MediaContainer {
 
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
 
-        dyn_images.iter_images_mut(|f, _| {
+        dyn_images.iter_images_mut_fallible(|f, _| {
             let edges = imageproc::edges::canny(&f.to_luma8(), 50.0, 100.0);
             let more_edges = imageproc::edges::canny(&f.to_luma8(), 25.0, 50.0);
             let mut t = f.to_rgba16();
@@ -29,7 +29,7 @@ impl MediaContainer {
             let img = DynamicImage::ImageRgba8(DynamicImage::ImageRgba16(t).to_rgba8().into());
 
             softglow(&img, 1, 10, 45)
-        });
+        })?;
 
         Ok(MediaObject::DynamicImages(dyn_images))
     }
@@ -9,7 +9,7 @@ impl MediaContainer {
         let input = self.pop_input()?;
 
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
-        dyn_images.iter_images_mut(|f, _| paint(f));
+        dyn_images.iter_images_mut_fallible(|f, _| paint(f))?;
         Ok(MediaObject::DynamicImages(dyn_images))
     }
 }
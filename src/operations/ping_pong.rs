@@ -9,7 +9,7 @@ impl MediaContainer {
     pub fn ping_pong(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return Ok(MediaObject::Encoded(ffmpeg_operations::gloop_video(v?)?));
         } else if let MediaObject::DynamicImages(ref d) = input
             && d.images.len() == 1
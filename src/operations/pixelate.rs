@@ -11,7 +11,7 @@ impl MediaContainer {
     pub fn pixelate(&self, strength: Option<f32>) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             let v = v?;
 
             let (v_w, v_h) = get_video_dimensions(v)?;
@@ -23,9 +23,10 @@ impl MediaContainer {
         } else {
             let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
 
-            let px_height = dyn_images.maybe_first()?.0.height() as f32 / strength.unwrap_or(10.0) as f32;
+            // strengths larger than the image would otherwise shrink it to nothing
+            let px_height = (dyn_images.maybe_first()?.0.height() as f32 / strength.unwrap_or(10.0).max(1.0)).max(1.0);
             dyn_images.iter_images_mut(|f, _| {
-                let new_width = (f.width() as f32 * (px_height / f.height() as f32)).round() as u32;
+                let new_width = ((f.width() as f32 * (px_height / f.height() as f32)).round() as u32).max(1);
 
                 let fb = FrameBufferOwned::new_from_dyn_image(f);
                 let smaller = ops::resize::nearest(fb.fb(), new_width as usize, px_height as usize);
@@ -1,13 +1,32 @@
 use image::{GenericImage, Rgba};
 
+use crate::core::error::FluxError;
 use crate::core::media_container::MediaContainer;
 use crate::processing::media_object::MediaObject;
 
 use super::OperationResult;
 
+/// Parses an RGBA (or RGB, assumed opaque) hex colour.
+fn parse_colour(col: &str) -> Result<u32, FluxError> {
+    let col = col.trim().trim_start_matches('#');
+    let parsed = u32::from_str_radix(col, 16)
+        .map_err(|_| FluxError::ParameterError(format!("Invalid colour {col}: expected a hex colour")))?;
+
+    match col.len() {
+        6 => Ok(parsed << 8 | 0xff),
+        8 => Ok(parsed),
+        _ => Err(FluxError::ParameterError(format!(
+            "Invalid colour {col}: expected 6 or 8 hex digits"
+        ))),
+    }
+}
+
 impl MediaContainer {
     pub fn posterize(&self, cols: &str) -> OperationResult {
-        let c: Vec<u32> = cols.split(',').map(|x| u32::from_str_radix(x, 16).unwrap()).collect();
+        let c = cols
+            .split(',')
+            .map(parse_colour)
+            .collect::<Result<Vec<u32>, FluxError>>()?;
         let input = self.pop_input()?;
 
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
@@ -15,7 +34,7 @@ impl MediaContainer {
         dyn_images.iter_images_mut(|f, _| {
             let grey = f.to_luma8();
             for (x, y, px) in grey.enumerate_pixels() {
-                let stage = (px.0[0] as f64 * c.len() as f64 / 255.0).floor() as usize;
+                let stage = ((px.0[0] as f64 * c.len() as f64 / 255.0).floor() as usize).min(c.len() - 1);
                 f.put_pixel(x, y, Rgba::from(c[stage].to_be_bytes()));
             }
 
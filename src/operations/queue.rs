@@ -0,0 +1,69 @@
+use crate::core::error::FluxError;
+use crate::core::media_container::MediaContainer;
+
+fn too_few_inputs(operation: &str, needed: usize) -> FluxError {
+    FluxError::ParameterError(format!("{operation} requires at least {needed} input(s) in the queue"))
+}
+
+impl MediaContainer {
+    /// Duplicates the next input, so that it will be popped twice.
+    pub fn dup(&self) -> Result<(), FluxError> {
+        if !self.input_queue.duplicate(0) {
+            return Err(too_few_inputs("dup", 1));
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the next two inputs.
+    pub fn swap(&self) -> Result<(), FluxError> {
+        if !self.input_queue.swap(0, 1) {
+            return Err(too_few_inputs("swap", 2));
+        }
+
+        Ok(())
+    }
+
+    /// Discards the next input.
+    pub fn drop_input(&self) -> Result<(), FluxError> {
+        self.input_queue.remove(0).ok_or(too_few_inputs("drop", 1))?;
+
+        Ok(())
+    }
+
+    /// Moves the next `n` inputs to the back of the queue.
+    pub fn roll(&self, n: Option<i64>) -> Result<(), FluxError> {
+        if self.input_queue.len() == 0 {
+            return Err(too_few_inputs("roll", 1));
+        }
+
+        self.input_queue.rotate(n.unwrap_or(1));
+
+        Ok(())
+    }
+
+    /// Moves the input at `index` to the front of the queue, so that it will be popped next.
+    pub fn select(&self, index: u64) -> Result<(), FluxError> {
+        if !self.input_queue.move_to_front(index as usize) {
+            return Err(too_few_inputs("select", (index as usize).saturating_add(1)));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::media_container::MediaContainer;
+    use crate::processing::media_object::MediaObject;
+
+    #[test]
+    fn select_past_the_end_fails() {
+        let container = MediaContainer::new();
+        container.push_input(MediaObject::Encoded(vec![]));
+
+        assert!(container.select(1).is_err());
+        assert!(container.select(u64::MAX).is_err());
+        assert!(container.select(0).is_ok());
+    }
+}
@@ -10,7 +10,7 @@ impl MediaContainer {
     pub fn rainbow(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return ffmpeg_operations::rainbow_video(v?).map(MediaObject::Encoded);
         };
 
@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+use crate::core::doctor::{self, Dependency};
+use crate::core::error::FluxError;
+use crate::util::edit_distance;
+
+/// The type of value an operation option accepts.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionType {
+    U64,
+    I64,
+    F32,
+    /// Either `0` or `1`.
+    Bool,
+    Str,
+}
+impl OptionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptionType::U64 => "u64",
+            OptionType::I64 => "i64",
+            OptionType::F32 => "f32",
+            OptionType::Bool => "bool",
+            OptionType::Str => "str",
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            OptionType::U64 => "a positive integer",
+            OptionType::I64 => "an integer",
+            OptionType::F32 => "a number",
+            OptionType::Bool => "either 0 or 1",
+            OptionType::Str => "text",
+        }
+    }
+}
+
+/// Inclusive range of values accepted by a numeric option.
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct OptionRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Description of a single option accepted by an operation, e.g. `depth` in `ghost[depth=10]`.
+#[derive(Serialize, Debug)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub ty: OptionType,
+    pub description: &'static str,
+    pub default: Option<&'static str>,
+    pub range: Option<OptionRange>,
+    pub required: bool,
+}
+impl OptionSpec {
+    pub const fn new(name: &'static str, ty: OptionType, description: &'static str) -> Self {
+        Self {
+            name,
+            ty,
+            description,
+            default: None,
+            range: None,
+            required: false,
+        }
+    }
+
+    pub const fn default(mut self, default: &'static str) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    pub const fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some(OptionRange { min, max });
+        self
+    }
+
+    pub const fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Checks that a value is of the correct type for this option and within its range.
+    pub fn validate(&self, value: &str) -> Result<(), FluxError> {
+        let number = match self.ty {
+            OptionType::U64 => value.parse::<u64>().ok().map(|v| v as f64),
+            OptionType::I64 => value.parse::<i64>().ok().map(|v| v as f64),
+            OptionType::F32 => value.parse::<f32>().ok().filter(|v| v.is_finite()).map(f64::from),
+            OptionType::Bool if value == "0" || value == "1" => return Ok(()),
+            OptionType::Bool => None,
+            OptionType::Str => return Ok(()),
+        };
+
+        let number = number.ok_or(FluxError::ParameterError(format!(
+            "Invalid value {value} for option {}: expected {}",
+            self.name,
+            self.ty.describe()
+        )))?;
+
+        if let Some(range) = self.range
+            && (number < range.min || number > range.max)
+        {
+            return Err(FluxError::ParameterError(format!(
+                "Invalid value {value} for option {}: must be between {} and {} inclusive",
+                self.name, range.min, range.max
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Description of an operation: what it is called, how many inputs it pops from the input queue,
+/// and which options it accepts.
+#[derive(Serialize, Debug)]
+pub struct OperationSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub description: &'static str,
+    pub inputs: usize,
+    /// Names by which each input can be referenced in the options, e.g. `overlay[base=a;top=b]`
+    /// takes the inputs stored as `a` and `b` instead of popping them from the input queue.
+    pub input_names: &'static [&'static str],
+    pub video: bool,
+    /// Whether this operation reorders the input queue instead of producing a result.
+    pub queue: bool,
+    pub options: &'static [OptionSpec],
+    /// External tools and files this operation needs, e.g. gegl or an asset. This does not include
+    /// ffmpeg for operations that only use it for video inputs.
+    pub requires: &'static [Dependency],
+}
+impl OperationSpec {
+    pub const fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            description,
+            inputs: 1,
+            input_names: &[],
+            video: true,
+            queue: false,
+            options: &[],
+            requires: &[],
+        }
+    }
+
+    pub const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    pub const fn inputs(mut self, inputs: usize) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Names each input, in the order they are popped. This also sets the number of inputs.
+    pub const fn input_names(mut self, input_names: &'static [&'static str]) -> Self {
+        self.inputs = input_names.len();
+        self.input_names = input_names;
+        self
+    }
+
+    pub const fn no_video(mut self) -> Self {
+        self.video = false;
+        self
+    }
+
+    pub const fn queue(mut self) -> Self {
+        self.queue = true;
+        self
+    }
+
+    pub const fn options(mut self, options: &'static [OptionSpec]) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub const fn requires(mut self, requires: &'static [Dependency]) -> Self {
+        self.requires = requires;
+        self
+    }
+
+    /// Checks that every dependency of this operation is available, so that an operation that
+    /// cannot run is refused up front rather than failing part way through a pipeline.
+    pub fn check_available(&self) -> Result<(), FluxError> {
+        for dependency in self.requires {
+            let status = doctor::check(*dependency);
+            if !status.available {
+                return Err(FluxError::OperationUnavailable(
+                    self.name.to_owned(),
+                    status.error.unwrap_or_else(|| format!("{dependency} is unavailable")),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_option(&self, name: &str) -> Option<&'static OptionSpec> {
+        self.options.iter().find(|o| o.name == name)
+    }
+
+    /// Checks that every option given is accepted by this operation and has a valid value, and
+    /// that no required options are missing.
+    pub fn validate_options(&self, options: &HashMap<String, String>) -> Result<(), FluxError> {
+        match self.option_problems(options).into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(()),
+        }
+    }
+
+    /// Like `validate_options`, but returns every problem instead of stopping at the first.
+    pub fn option_problems(&self, options: &HashMap<String, String>) -> Vec<FluxError> {
+        let mut problems = vec![];
+
+        // sorted so that problems are always reported in the same order
+        let mut keys = options.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        for key in keys {
+            let Some(option) = self.get_option(key) else {
+                let suggestion = closest_match(key, self.options.iter().map(|o| o.name));
+                problems.push(FluxError::ParameterError(format!(
+                    "Unrecognised option {key} for operation {}{}",
+                    self.name,
+                    did_you_mean(suggestion)
+                )));
+                continue;
+            };
+
+            if let Err(e) = option.validate(&options[key]) {
+                problems.push(e);
+            }
+        }
+
+        for missing in self
+            .options
+            .iter()
+            .filter(|o| o.required && !options.contains_key(o.name))
+        {
+            problems.push(FluxError::ParameterError(format!(
+                "Missing required option {} for operation {}",
+                missing.name, self.name
+            )));
+        }
+
+        problems
+    }
+}
+impl Display for OperationSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {}", self.name, self.description)?;
+        if !self.aliases.is_empty() {
+            writeln!(f, "Aliases: {}", self.aliases.join(", "))?;
+        }
+        if self.input_names.is_empty() {
+            writeln!(f, "Inputs: {}", self.inputs)?;
+        } else {
+            writeln!(f, "Inputs: {} ({})", self.inputs, self.input_names.join(", "))?;
+        }
+        writeln!(f, "Video: {}", if self.video { "supported" } else { "unsupported" })?;
+        if !self.requires.is_empty() {
+            let requires = self.requires.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            writeln!(f, "Requires: {}", requires.join(", "))?;
+        }
+
+        if self.options.is_empty() {
+            write!(f, "Options: none")?;
+        } else {
+            write!(f, "Options:")?;
+            for option in self.options {
+                write!(f, "\n  {} ({}", option.name, option.ty.as_str())?;
+                if option.required {
+                    write!(f, ", required")?;
+                }
+                if let Some(default) = option.default {
+                    write!(f, ", default {default}")?;
+                }
+                if let Some(range) = option.range {
+                    write!(f, ", {} to {}", range.min, range.max)?;
+                }
+                write!(f, "): {}", option.description)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks up an operation by name or alias.
+pub fn get_operation(name: &str) -> Option<&'static OperationSpec> {
+    OPERATIONS.iter().find(|o| o.name == name || o.aliases.contains(&name))
+}
+
+/// Looks up an operation by name or alias, suggesting a similarly-named operation if it does not
+/// exist.
+pub fn find_operation(name: &str) -> Result<&'static OperationSpec, FluxError> {
+    get_operation(name).ok_or_else(|| {
+        let names = OPERATIONS
+            .iter()
+            .flat_map(|o| [o.name].into_iter().chain(o.aliases.iter().copied()));
+        FluxError::ParameterError(format!(
+            "Unrecognised operation {name}{}",
+            did_you_mean(closest_match(name, names))
+        ))
+    })
+}
+
+/// Finds the candidate most similar to `name`, if any are similar enough to be a likely typo.
+fn closest_match<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.len() / 3).max(2);
+
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn did_you_mean(suggestion: Option<&str>) -> String {
+    suggestion.map(|s| format!(" (did you mean {s}?)")).unwrap_or_default()
+}
+
+use Dependency::*;
+use OptionType::*;
+
+/// Every operation supported by Flux. This must be kept in sync with
+/// `MediaContainer::perform_operation`, which the tests in `operations` check.
+pub static OPERATIONS: &[OperationSpec] = &[
+    OperationSpec::new(
+        "ah-shit",
+        "Overlays the input on the \"ah shit, here we go again\" video.",
+    )
+    .requires(&[Ffmpeg, Asset("./assets/video/ahshit.mp4")]),
+    OperationSpec::new(
+        "april-fools",
+        "Appends the April Fools video to the first frame of the input.",
+    )
+    .requires(&[Ffmpeg, Asset("./assets/video/april.mp4")]),
+    OperationSpec::new("back-tattoo", "Tattoos the first frame of the input onto a back.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/back-tattoo.zip")]),
+    OperationSpec::new(
+        "billboard",
+        "Puts the first frame of the input on a billboard in a cityscape.",
+    )
+    .requires(&[Makesweet, Asset("./makesweet/templates/billboard-cityscape.zip")]),
+    OperationSpec::new("bloom", "Adds a glowing bloom effect.")
+        .requires(&[Gegl])
+        .options(&[
+            OptionSpec::new("radius", U64, "Radius of the glow.")
+                .default("5")
+                .range(1.0, 50.0),
+            OptionSpec::new("brightness", U64, "Brightness of the glow.")
+                .default("35")
+                .range(0.0, 100.0),
+            OptionSpec::new("sharpness", U64, "Sharpness of the glow.")
+                .default("85")
+                .range(0.0, 100.0),
+        ]),
+    OperationSpec::new("blur", "Applies a gaussian blur.").options(&[OptionSpec::new(
+        "strength",
+        F32,
+        "Blur strength.",
+    )
+    .default("3")]),
+    OperationSpec::new("book", "Puts the first frame of the input on the cover of a book.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/book.zip")]),
+    OperationSpec::new("caption", "Adds a caption above or below the input.")
+        .requires(&[Vips])
+        .options(&[
+            OptionSpec::new("text", Str, "Caption text.").required(),
+            OptionSpec::new("bottom", Bool, "Place the caption below the input.").default("0"),
+            OptionSpec::new("black", Bool, "Use white text on a black background.").default("0"),
+        ]),
+    OperationSpec::new("circuitboard", "Puts the first frame of the input on a circuit board.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/circuitboard.zip")]),
+    OperationSpec::new("deepfry", "Deep fries the input."),
+    OperationSpec::new("drip", "Adds the drip music to the input, producing a video.")
+        .requires(&[Ffmpeg, Asset("./assets/audio/drip.mp3")]),
+    OperationSpec::new(
+        "femurbreaker",
+        "Adds the femur breaker sound to the input, producing a video.",
+    )
+    .requires(&[Ffmpeg, Asset("./assets/audio/femurbreaker.mp3")]),
+    OperationSpec::new("fisheye", "Applies a fisheye lens distortion.").requires(&[Gegl]),
+    OperationSpec::new("flag", "Waves the first frame of the input as a flag.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/flag.zip")]),
+    OperationSpec::new("flag2", "Waves the first frame of the input as a flag on a pole.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/flag2.zip")]),
+    OperationSpec::new("flip", "Flips the input vertically."),
+    OperationSpec::new("flop", "Flips the input horizontally."),
+    OperationSpec::new(
+        "fortune-cookie",
+        "Puts the first frame of the input inside a fortune cookie.",
+    )
+    .requires(&[Makesweet, Asset("./makesweet/templates/fortune-cookie.zip")]),
+    OperationSpec::new(
+        "frame-shift",
+        "Takes each row of pixels from a later frame. Requires more than one frame.",
+    ),
+    OperationSpec::new(
+        "frames",
+        "Extracts every frame of the input into a ZIP archive of PNG images.",
+    ),
+    OperationSpec::new(
+        "ghost",
+        "Blends each frame with the frames before it. Requires more than one frame.",
+    )
+    .options(&[OptionSpec::new("depth", U64, "Number of previous frames to blend.")
+        .default("3")
+        .range(1.0, 20.0)]),
+    OperationSpec::new("gif", "Converts the input to a GIF."),
+    OperationSpec::new("gif-magik", "Repeatedly seam-carves a still image into an animation.").no_video(),
+    OperationSpec::new("globe", "Wraps the input around a globe.").requires(&[Gegl]),
+    OperationSpec::new("grayscale", "Converts the input to grayscale.").aliases(&["greyscale"]),
+    OperationSpec::new(
+        "heart-locket",
+        "Puts two inputs into a heart locket. Only one input is used if `text` is given.",
+    )
+    .requires(&[Makesweet, Asset("./makesweet/templates/heart-locket.zip")])
+    .input_names(&["left", "right"])
+    .options(&[OptionSpec::new(
+        "text",
+        Str,
+        "Text to show in the second half of the locket instead of an image.",
+    )]),
+    OperationSpec::new("invert", "Inverts the colours of the input."),
+    OperationSpec::new("jpeg", "Applies heavy JPEG compression.").options(&[OptionSpec::new(
+        "quality",
+        U64,
+        "JPEG quality, lower is worse.",
+    )
+    .default("5")
+    .range(1.0, 100.0)]),
+    OperationSpec::new("magik", "Seam-carves the input."),
+    OperationSpec::new("meme", "Adds top and/or bottom meme text.")
+        .requires(&[Vips])
+        .options(&[
+            OptionSpec::new("top", Str, "Top text."),
+            OptionSpec::new("bottom", Str, "Bottom text."),
+        ]),
+    OperationSpec::new("motivate", "Creates a motivational poster from the input.")
+        .requires(&[Vips])
+        .options(&[
+            OptionSpec::new("top", Str, "Top text."),
+            OptionSpec::new("bottom", Str, "Bottom text."),
+        ]),
+    OperationSpec::new("neon", "Highlights the edges of the input with a neon glow.").requires(&[Gegl]),
+    OperationSpec::new("overlay", "Overlays the second input on top of the first.")
+        .input_names(&["base", "top"])
+        .no_video(),
+    OperationSpec::new("paint", "Makes the input look like a painting.").requires(&[Gegl]),
+    OperationSpec::new(
+        "ping-pong",
+        "Plays the input forwards and then backwards. Requires more than one frame.",
+    ),
+    OperationSpec::new("pixelate", "Pixelates the input.").options(&[OptionSpec::new(
+        "strength",
+        F32,
+        "Pixelation strength.",
+    )
+    .default("10")
+    .range(1.0, 1000.0)]),
+    OperationSpec::new(
+        "posterize",
+        "Maps the brightness of the input onto a palette of colours.",
+    )
+    .options(&[OptionSpec::new(
+        "cols",
+        Str,
+        "Comma-separated list of RGB or RGBA hex colours, e.g. ff0000,0000ffff.",
+    )
+    .required()]),
+    OperationSpec::new("rainbow", "Cycles the hue of the input."),
+    OperationSpec::new("resize", "Resizes the input. Defaults to doubling its size.").options(&[
+        OptionSpec::new("width", U64, "Output width.").range(1.0, 16384.0),
+        OptionSpec::new("height", U64, "Output height.").range(1.0, 16384.0),
+        OptionSpec::new("scale", F32, "Scale factor. Takes priority over width and height.").range(0.01, 16.0),
+    ]),
+    OperationSpec::new("reverse", "Reverses the input. Requires more than one frame."),
+    OperationSpec::new("rotate", "Rotates the input.").options(&[OptionSpec::new(
+        "degrees",
+        U64,
+        "Angle to rotate by, in degrees.",
+    )
+    .default("90")
+    .range(0.0, 360.0)]),
+    OperationSpec::new("rubiks", "Puts the first frame of the input on a Rubik's cube.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/rubiks.zip")]),
+    OperationSpec::new(
+        "scramble",
+        "Shuffles the frames of the input. Requires more than one frame.",
+    ),
+    OperationSpec::new("set-loop", "Sets the number of times an animation loops.")
+        .no_video()
+        .options(&[OptionSpec::new("loops", I64, "Loop count, or -1 to loop forever.")
+            .default("-1")
+            .range(-1.0, 65535.0)]),
+    OperationSpec::new("siren", "Adds a siren sound to the input, producing a video.")
+        .requires(&[Ffmpeg, Asset("./assets/audio/siren.mp3")]),
+    OperationSpec::new("speech-bubble", "Cuts a speech bubble into the top of the input.")
+        .requires(&[Asset("./assets/image/speechbubble.png")])
+        .options(&[OptionSpec::new(
+            "solid",
+            Bool,
+            "Fill the speech bubble instead of making it transparent.",
+        )
+        .default("0")]),
+    OperationSpec::new("speed", "Changes the playback speed of the input.").options(&[OptionSpec::new(
+        "multiplier",
+        F32,
+        "Speed multiplier.",
+    )
+    .default("1.5")
+    .range(0.1, 15.0)]),
+    OperationSpec::new("spin", "Spins the input."),
+    OperationSpec::new("spread", "Randomly displaces the pixels of the input.").options(&[OptionSpec::new(
+        "strength",
+        U64,
+        "Maximum pixel displacement.",
+    )
+    .default("10")
+    .range(0.0, 1000.0)]),
+    OperationSpec::new(
+        "sweden",
+        "Adds the Minecraft Sweden music to the input, producing a video.",
+    )
+    .requires(&[Ffmpeg, Asset("./assets/audio/sweden.mp3")]),
+    OperationSpec::new("swirl", "Swirls the input.")
+        .options(&[OptionSpec::new("strength", F32, "Swirl strength.")
+            .default("1")
+            .range(-100.0, 100.0)]),
+    OperationSpec::new("terraria", "Adds Terraria music to the input, producing a video.")
+        .requires(&[Ffmpeg, Asset("./assets/audio/terraria.mp3")]),
+    OperationSpec::new("toaster", "Puts the first frame of the input on a toaster.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/toaster.zip")]),
+    OperationSpec::new("uncaption", "Removes a caption from the top of the input.").options(&[OptionSpec::new(
+        "amount",
+        Str,
+        "Number of rows, or a percentage of the height (e.g. 20%), to remove. Detected automatically if omitted.",
+    )]),
+    OperationSpec::new("valentine", "Puts the first frame of the input on a valentine's card.")
+        .requires(&[Makesweet, Asset("./makesweet/templates/valentine.zip")]),
+    OperationSpec::new("wormhole", "Sucks the input into a wormhole."),
+    OperationSpec::new("zoom", "Zooms into the input."),
+    OperationSpec::new("zoom-blur", "Applies a zoom blur.")
+        .requires(&[Gegl])
+        .options(&[
+            OptionSpec::new("power", F32, "Blur power. Negative values zoom outwards.")
+                .default("2")
+                .range(-10.0, 10.0),
+        ]),
+    OperationSpec::new(
+        "threshold",
+        "Converts the input to black and white at a brightness threshold.",
+    )
+    .options(&[OptionSpec::new("threshold", F32, "Brightness threshold.")
+        .default("0.5")
+        .range(0.0, 1.0)]),
+    OperationSpec::new("channels", "Keeps only the given colour channels.").options(&[OptionSpec::new(
+        "keep",
+        Str,
+        "Channels to keep, any combination of r, g and b.",
+    )
+    .default("rgb")]),
+    OperationSpec::new("edges", "Detects the edges of the input."),
+    // queue ops
+    OperationSpec::new("dup", "Duplicates the next input in the queue.").queue(),
+    OperationSpec::new("swap", "Swaps the next two inputs in the queue.")
+        .inputs(2)
+        .queue(),
+    OperationSpec::new("drop", "Discards the next input in the queue.").queue(),
+    OperationSpec::new("roll", "Moves the next inputs in the queue to the back of the queue.")
+        .queue()
+        .options(&[OptionSpec::new(
+            "n",
+            I64,
+            "Number of inputs to move. Negative values move inputs from the back to the front instead.",
+        )
+        .default("1")]),
+    OperationSpec::new(
+        "select",
+        "Moves an input to the front of the queue, so that it is used next.",
+    )
+    .queue()
+    .options(&[OptionSpec::new("index", U64, "Index of the input in the queue, starting from 0.").required()]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(operation: &str, options: &[(&str, &str)]) -> Option<String> {
+        let options = options
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        find_operation(operation)
+            .unwrap()
+            .validate_options(&options)
+            .err()
+            .map(|e| e.to_string())
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        for (operation, key, value) in [
+            ("pixelate", "strength", "0"),
+            ("pixelate", "strength", "-5"),
+            ("resize", "scale", "0"),
+            ("resize", "scale", "-1"),
+            ("resize", "width", "0"),
+            ("resize", "height", "100000"),
+            ("spread", "strength", "18446744073709551615"),
+            ("swirl", "strength", "1e30"),
+        ] {
+            let result = problem(operation, &[(key, value)]);
+            assert!(
+                result.as_ref().is_some_and(|p| p.contains("must be between")),
+                "{operation}[{key}={value}] gave {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_in_range_values() {
+        assert_eq!(problem("pixelate", &[("strength", "1")]), None);
+        assert_eq!(problem("resize", &[("scale", "0.5")]), None);
+        assert_eq!(problem("resize", &[("width", "640"), ("height", "480")]), None);
+        assert_eq!(problem("spread", &[("strength", "0")]), None);
+        assert_eq!(problem("swirl", &[("strength", "-2.5")]), None);
+    }
+
+    #[test]
+    fn rejects_unknown_options_with_suggestion() {
+        let typo = problem("resize", &[("scael", "2")]).unwrap();
+        assert!(typo.contains("Unrecognised option scael"), "{typo}");
+        assert!(typo.contains("did you mean scale?"), "{typo}");
+
+        let unknown = problem("pixelate", &[("xyzzy", "2")]).unwrap();
+        assert!(unknown.contains("Unrecognised option xyzzy"), "{unknown}");
+        assert!(!unknown.contains("did you mean"), "{unknown}");
+    }
+
+    #[test]
+    fn suggests_similar_operations() {
+        let err = find_operation("pixelat").unwrap_err().to_string();
+        assert!(err.contains("did you mean pixelate?"), "{err}");
+    }
+}
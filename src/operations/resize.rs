@@ -9,6 +9,9 @@ use crate::processing::type_conversion::framebuffer_to_dyn_image;
 
 use super::OperationResult;
 
+/// Largest width or height a video can be resized to.
+const MAX_VIDEO_SIZE: u64 = 16384;
+
 pub struct ResizeOptions {
     pub width: Option<u64>,
     pub height: Option<u64>,
@@ -19,7 +22,7 @@ impl MediaContainer {
     pub fn resize(&self, options: ResizeOptions) -> OperationResult {
         let input = self.pop_input()?;
 
-        let (mut width, mut height) = if let Some(input) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        let (mut width, mut height) = if let Some(input) = input.try_encoded_video(&self.limits) {
             let (w, h) = get_video_dimensions(input?)?;
             (w as u64, h as u64)
         } else {
@@ -44,7 +47,9 @@ impl MediaContainer {
             },
         }
 
-        let out = if let Some(input) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        let out = if let Some(input) = input.try_encoded_video(&self.limits) {
+            // a large scale can otherwise take a video past what ffmpeg can encode
+            let (width, height) = (width.clamp(2, MAX_VIDEO_SIZE), height.clamp(2, MAX_VIDEO_SIZE));
             let real_width = if width % 2 == 1 { width + 1 } else { width };
             let real_height = if height % 2 == 1 { height + 1 } else { height };
             let out = ffmpeg_operations::resize_video(input?, real_width as usize, real_height as usize)?;
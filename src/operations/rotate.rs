@@ -11,7 +11,7 @@ impl MediaContainer {
     pub fn rotate(&self, deg: Option<u64>) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return ffmpeg_operations::rotate_video(v?, deg.unwrap_or(90) as usize).map(MediaObject::Encoded);
         };
 
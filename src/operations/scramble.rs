@@ -1,5 +1,4 @@
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
 
 use crate::core::error::FluxError;
 use crate::core::media_container::MediaContainer;
@@ -12,8 +11,8 @@ impl MediaContainer {
     pub fn scramble(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
-            let s = scramble_video(v?)?;
+        if let Some(v) = input.try_encoded_video(&self.limits) {
+            let s = scramble_video(v?, &mut *self.rng())?;
 
             return Ok(MediaObject::Encoded(s));
         }
@@ -23,7 +22,7 @@ impl MediaContainer {
             return Err(FluxError::SinglePageMediaUnsupported);
         };
 
-        dyn_images.images.shuffle(&mut thread_rng());
+        dyn_images.images.shuffle(&mut *self.rng());
         Ok(MediaObject::DynamicImages(dyn_images))
     }
 }
@@ -12,7 +12,7 @@ impl MediaContainer {
         let input = self.pop_input()?;
         let multiplier = multiplier.unwrap_or(1.5).clamp(0.1, 15.0);
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return Ok(MediaObject::Encoded(ffmpeg_operations::speed_video(v?, multiplier)?));
         };
 
@@ -13,7 +13,7 @@ impl MediaContainer {
     pub fn spin(&self) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return ffmpeg_operations::spin_video(v?).map(MediaObject::Encoded);
         };
 
@@ -1,3 +1,6 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
 use crate::core::media_container::MediaContainer;
 use crate::processing::media_object::MediaObject;
 use crate::processing::spread_image;
@@ -11,8 +14,12 @@ impl MediaContainer {
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
         let strength = strength.unwrap_or(10);
 
-        dyn_images.iter_images_mut(|f, _| {
-            spread_image(f, strength as usize);
+        // frames are processed in parallel, so each gets its own generator to keep the output the
+        // same for the same seed
+        let seed = self.next_seed();
+        dyn_images.iter_images_mut(|f, i| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+            spread_image(f, strength as usize, &mut rng);
             f.clone()
         });
 
@@ -71,7 +71,7 @@ impl MediaContainer {
     pub fn uncaption(&self, amount: Option<&str>) -> OperationResult {
         let input = self.pop_input()?;
 
-        if let Some(v) = input.try_encoded_video(self.limits.video_decode_permitted) {
+        if let Some(v) = input.try_encoded_video(&self.limits) {
             return uncaption_video(v?, amount).map(MediaObject::Encoded);
         }
 
@@ -10,7 +10,7 @@ impl MediaContainer {
         let power = power.unwrap_or(2.0).clamp(-10.0, 10.0) / 10.0;
 
         let mut dyn_images = input.to_dynamic_images(&self.limits)?.into_owned();
-        dyn_images.iter_images_mut(|f, _| zoom_blur(f, power));
+        dyn_images.iter_images_mut_fallible(|f, _| zoom_blur(f, power))?;
 
         Ok(MediaObject::DynamicImages(dyn_images))
     }
@@ -1,9 +1,11 @@
 use std::io::Cursor;
+use std::time::Instant;
 
 use image::codecs::gif::{GifDecoder, Repeat};
 use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
 use image::imageops::FilterType;
-use image::{load_from_memory, AnimationDecoder, Frame, ImageResult};
+use image::{load_from_memory, AnimationDecoder, Frame, ImageDecoder, ImageReader};
 
 use crate::core::error::FluxError;
 use crate::core::media_container::DecodeLimits;
@@ -11,33 +13,48 @@ use crate::processing::dynamic_image_wrapper::DynamicImageWrapper;
 use crate::processing::ffmpeg;
 use crate::processing::filetype::{get_sig_incl_mp4, Type};
 use crate::processing::gif::gif_get_repeat_count;
+use crate::processing::ico::ico_get_entries;
 use crate::processing::media_object::DynamicImagesMediaObject;
 use crate::processing::type_conversion::convert_frames_to_dynamic_images;
-use crate::vips::vips_transcode_to;
+use crate::processing::webp::{webp_get_repeat_count, webp_is_animated};
+use crate::util::profile::{self, MediaStats};
+use crate::vips::{vips_decode_pages, vips_render_svg, vips_transcode_to};
 
 pub fn decode_to_dynamic_images(input: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
+    let start = Instant::now();
     let filetype = get_sig_incl_mp4(input).ok_or(FluxError::UnsupportedFiletype)?;
 
     let mut dyn_images = match filetype {
-        Type::Jpeg => DynamicImagesMediaObject {
-            images: vec![DynamicImageWrapper::new_static(load_from_memory(input)?)],
-            audio: None,
-            repeat: Repeat::Infinite,
+        Type::Jpeg | Type::Bmp | Type::Qoi => {
+            check_still_pixel_limit(input, limits)?;
+            DynamicImagesMediaObject {
+                images: vec![DynamicImageWrapper::new_static(load_from_memory(input)?)],
+                audio: None,
+                repeat: Repeat::Infinite,
+            }
         },
-        Type::Png => decode_png_to_dynamic_images(input)?,
-        Type::Webp => decode_webp_to_dynamic_images(input)?,
-        Type::Gif => decode_gif_to_dynamic_images(input, limits.frame_limit)?,
+        Type::Png => decode_png_to_dynamic_images(input, limits)?,
+        Type::Webp => decode_webp_to_dynamic_images(input, limits)?,
+        Type::Gif => decode_gif_to_dynamic_images(input, limits)?,
+        Type::Ico => decode_ico_to_dynamic_images(input, limits)?,
+        Type::Svg => decode_svg_to_dynamic_images(input, limits)?,
+        Type::Tiff | Type::Avif | Type::Heic => decode_pages_to_dynamic_images(input, limits)?,
         Type::Webm | Type::Mp4 => {
             if !limits.video_decode_permitted {
-                return Err(FluxError::VideoDecodeDisabled);
+                return Err(FluxError::VideoDecodeDisabled(limits.video_decode_disabled_message()));
             }
             decode_video_to_dynamic_images(input, limits)?
         },
     };
 
+    // e.g. a GIF with no frames, or a video that ffmpeg could not extract any frames from
+    if dyn_images.images.is_empty() {
+        return Err(FluxError::CorruptInput("Input has no frames".to_owned()));
+    }
+
     // resize to fit any limits
     if let Some((w, h)) = limits.resolution_limit {
-        let first = &dyn_images.images.first().unwrap().0;
+        let first = &dyn_images.images[0].0;
         let (old_w, old_h) = (first.width(), first.height());
 
         if old_w as u64 > w && old_h as u64 > h {
@@ -51,20 +68,53 @@ pub fn decode_to_dynamic_images(input: &[u8], limits: &DecodeLimits) -> Result<D
         }
     }
 
+    profile::record_decode(start.elapsed(), MediaStats::of_images(&dyn_images));
     Ok(dyn_images)
 }
 
-pub fn decode_png_to_dynamic_images(buf: &[u8]) -> Result<DynamicImagesMediaObject, FluxError> {
+/// Checks the pixel limit for a still image by reading its dimensions, without decoding it.
+fn check_still_pixel_limit(buf: &[u8], limits: &DecodeLimits) -> Result<(), FluxError> {
+    if limits.pixel_limit.is_some() {
+        let (width, height) = ImageReader::new(Cursor::new(buf))
+            .with_guessed_format()?
+            .into_dimensions()?;
+        limits.check_pixel_limit(width as u64, height as u64, 1)?;
+    }
+
+    Ok(())
+}
+
+/// Decodes frames one at a time, stopping at the frame limit and checking the pixel limit as each
+/// frame is decoded. Every frame is the size of the full canvas once decoded.
+fn decode_frames<'a>(
+    decoder: impl AnimationDecoder<'a>,
+    (width, height): (u32, u32),
+    limits: &DecodeLimits,
+) -> Result<Vec<Frame>, FluxError> {
+    let frame_limit = limits.frame_limit.unwrap_or(u64::MAX);
+    let mut frames = vec![];
+    let mut decoder_frames = decoder.into_frames();
+
+    // the iterator decodes a frame each time it is advanced, so no frame past the frame limit is
+    // decoded, and at most one frame past the pixel limit is decoded before it is rejected
+    while (frames.len() as u64) < frame_limit {
+        let Some(frame) = decoder_frames.next() else {
+            break;
+        };
+        limits.check_pixel_limit(width as u64, height as u64, frames.len() as u64 + 1)?;
+        frames.push(frame?);
+    }
+
+    Ok(frames)
+}
+
+pub fn decode_png_to_dynamic_images(buf: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
     let cursor = Cursor::new(buf);
 
     let decoder = PngDecoder::new(cursor)?;
+    let dimensions = decoder.dimensions();
     if decoder.is_apng()? {
-        let apng_decoder = decoder.apng()?;
-        let into_frames = apng_decoder.into_frames();
-        let mut frames = vec![];
-        for frame in into_frames {
-            frames.push(frame?);
-        }
+        let frames = decode_frames(decoder.apng()?, dimensions, limits)?;
         let images = convert_frames_to_dynamic_images(frames);
 
         Ok(DynamicImagesMediaObject {
@@ -73,6 +123,7 @@ pub fn decode_png_to_dynamic_images(buf: &[u8]) -> Result<DynamicImagesMediaObje
             repeat: Repeat::Infinite,
         })
     } else {
+        limits.check_pixel_limit(dimensions.0 as u64, dimensions.1 as u64, 1)?;
         let dyn_image = load_from_memory(buf)?;
 
         let image = DynamicImageWrapper::new(dyn_image, None);
@@ -85,7 +136,20 @@ pub fn decode_png_to_dynamic_images(buf: &[u8]) -> Result<DynamicImagesMediaObje
     }
 }
 
-pub fn decode_webp_to_dynamic_images(buf: &[u8]) -> Result<DynamicImagesMediaObject, FluxError> {
+pub fn decode_webp_to_dynamic_images(buf: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
+    if webp_is_animated(buf) {
+        let decoder = WebPDecoder::new(Cursor::new(buf))?;
+        let dimensions = decoder.dimensions();
+        let frames = decode_frames(decoder, dimensions, limits)?;
+
+        return Ok(DynamicImagesMediaObject {
+            images: convert_frames_to_dynamic_images(frames),
+            repeat: webp_get_repeat_count(buf),
+            audio: None,
+        });
+    }
+
+    check_still_pixel_limit(buf, limits)?;
     let buf = vips_transcode_to(buf, ".png")?;
     let image = DynamicImageWrapper::new(load_from_memory(&buf)?, None);
     Ok(DynamicImagesMediaObject {
@@ -95,19 +159,73 @@ pub fn decode_webp_to_dynamic_images(buf: &[u8]) -> Result<DynamicImagesMediaObj
     })
 }
 
-pub fn decode_gif_to_dynamic_images(
+/// Decodes TIFF, AVIF and HEIC through libvips, with each page of a TIFF (or frame of an image
+/// sequence) as a frame.
+pub fn decode_pages_to_dynamic_images(
     buf: &[u8],
-    frame_limit: Option<u64>,
+    limits: &DecodeLimits,
 ) -> Result<DynamicImagesMediaObject, FluxError> {
+    let pages = vips_decode_pages(buf, limits.frame_limit, limits.pixel_limit)?;
+
+    Ok(DynamicImagesMediaObject {
+        images: pages
+            .into_iter()
+            .map(|(image, delay)| DynamicImageWrapper::new(image, delay))
+            .collect(),
+        repeat: Repeat::Infinite,
+        audio: None,
+    })
+}
+
+/// Rasterises an SVG, at the size set by `svg_size` or `svg_density`. Either way, it is rendered
+/// to fit within the resolution limit (or 4096x4096 if there is none) rather than resized after.
+pub fn decode_svg_to_dynamic_images(buf: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
+    const DEFAULT_MAX_SIZE: u64 = 4096;
+
+    let scale = limits.svg_density.unwrap_or(72.0) / 72.0;
+    let max = limits.resolution_limit.unwrap_or((DEFAULT_MAX_SIZE, DEFAULT_MAX_SIZE));
+    let (fit, enlarge) = match limits.svg_size {
+        Some((w, h)) => ((w.min(max.0), h.min(max.1)), true),
+        None => (max, false),
+    };
+    let fit = (fit.0.min(u32::MAX as u64) as u32, fit.1.min(u32::MAX as u64) as u32);
+
+    let image = vips_render_svg(buf, scale, Some(fit), enlarge, limits.pixel_limit)?;
+
+    Ok(DynamicImagesMediaObject {
+        images: vec![DynamicImageWrapper::new_static(image)],
+        repeat: Repeat::Infinite,
+        audio: None,
+    })
+}
+
+/// Decodes each size in an ICO as a frame, largest first. Frames keep their own sizes.
+pub fn decode_ico_to_dynamic_images(buf: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
+    let frame_limit = limits.frame_limit.unwrap_or(u64::MAX);
+    let entries = ico_get_entries(buf)
+        .into_iter()
+        .take(frame_limit.min(usize::MAX as u64) as usize)
+        .collect::<Vec<_>>();
+
+    let pixels = entries.iter().map(|e| e.width as u64 * e.height as u64).sum::<u64>();
+    limits.check_pixel_limit(pixels, 1, 1)?;
+
+    let images = entries
+        .iter()
+        .map(|e| Ok(DynamicImageWrapper::new_static(load_from_memory(&e.data)?)))
+        .collect::<Result<Vec<_>, FluxError>>()?;
+
+    Ok(DynamicImagesMediaObject {
+        images,
+        repeat: Repeat::Infinite,
+        audio: None,
+    })
+}
+
+pub fn decode_gif_to_dynamic_images(buf: &[u8], limits: &DecodeLimits) -> Result<DynamicImagesMediaObject, FluxError> {
     let decoder = GifDecoder::new(Cursor::new(buf))?;
-    let frames = decoder
-        .into_frames()
-        .take(if let Some(f) = frame_limit {
-            f as usize
-        } else {
-            usize::MAX
-        })
-        .collect::<ImageResult<Vec<Frame>>>()?;
+    let dimensions = decoder.dimensions();
+    let frames = decode_frames(decoder, dimensions, limits)?;
 
     let repeats = gif_get_repeat_count(buf);
 
@@ -125,7 +243,7 @@ pub fn decode_video_to_dynamic_images(
     limits: &DecodeLimits,
 ) -> Result<DynamicImagesMediaObject, FluxError> {
     if !limits.video_decode_permitted {
-        return Err(FluxError::VideoDecodeDisabled);
+        return Err(FluxError::VideoDecodeDisabled(limits.video_decode_disabled_message()));
     }
 
     let split = ffmpeg::split_video(buf, limits.clone())?;
@@ -136,9 +254,106 @@ pub fn decode_video_to_dynamic_images(
             .into_iter()
             .map(|x| DynamicImageWrapper::new(x, None))
             .collect::<Vec<_>>(),
-        audio: Some(split.1),
+        audio: split.1,
         repeat: Repeat::Infinite,
     };
 
     Ok(object)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use image::codecs::gif::Repeat;
+    use image::{Delay, DynamicImage, RgbaImage};
+
+    use super::*;
+    use crate::processing::encode::{apng, gif};
+
+    fn frames(count: usize) -> Vec<RgbaImage> {
+        (0..count)
+            .map(|i| RgbaImage::from_pixel(4, 4, image::Rgba([i as u8, 0, 0, 255])))
+            .collect()
+    }
+
+    fn test_gif(count: usize) -> Vec<u8> {
+        let frames = frames(count)
+            .into_iter()
+            .map(|f| {
+                (
+                    DynamicImage::ImageRgba8(f),
+                    Delay::from_saturating_duration(Duration::from_millis(100)),
+                )
+            })
+            .collect();
+        gif::encode(frames, 4, 4, Repeat::Infinite).unwrap()
+    }
+
+    fn test_apng(count: usize) -> Vec<u8> {
+        let frames = frames(count)
+            .into_iter()
+            .map(|f| (f, Duration::from_millis(100)))
+            .collect();
+        apng::encode(frames, 4, 4, Repeat::Infinite).unwrap()
+    }
+
+    fn limits(frame_limit: Option<u64>, pixel_limit: Option<u64>) -> DecodeLimits {
+        DecodeLimits {
+            frame_limit,
+            pixel_limit,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn frame_limit_truncates_gif() {
+        let decoded = decode_gif_to_dynamic_images(&test_gif(3), &limits(Some(2), None)).unwrap();
+        assert_eq!(decoded.images.len(), 2);
+    }
+
+    #[test]
+    fn frame_limit_truncates_apng() {
+        let decoded = decode_png_to_dynamic_images(&test_apng(3), &limits(Some(2), None)).unwrap();
+        assert_eq!(decoded.images.len(), 2);
+    }
+
+    #[test]
+    fn pixel_limit_rejects_gif() {
+        // 4x4 frames, so only the first frame fits within 20 pixels
+        let result = decode_gif_to_dynamic_images(&test_gif(3), &limits(None, Some(20)));
+        assert!(matches!(result, Err(FluxError::PixelLimitExceeded(20))));
+    }
+
+    #[test]
+    fn pixel_limit_rejects_apng() {
+        let result = decode_png_to_dynamic_images(&test_apng(3), &limits(None, Some(20)));
+        assert!(matches!(result, Err(FluxError::PixelLimitExceeded(20))));
+    }
+
+    #[test]
+    fn pixel_limit_accepts_gif_at_limit() {
+        // 4x4 frames, so each input is exactly at its limit
+        let decoded = decode_gif_to_dynamic_images(&test_gif(1), &limits(None, Some(16))).unwrap();
+        assert_eq!(decoded.images.len(), 1);
+
+        let decoded = decode_gif_to_dynamic_images(&test_gif(3), &limits(None, Some(48))).unwrap();
+        assert_eq!(decoded.images.len(), 3);
+    }
+
+    #[test]
+    fn pixel_limit_accepts_apng_at_limit() {
+        let decoded = decode_png_to_dynamic_images(&test_apng(1), &limits(None, Some(16))).unwrap();
+        assert_eq!(decoded.images.len(), 1);
+
+        let decoded = decode_png_to_dynamic_images(&test_apng(3), &limits(None, Some(48))).unwrap();
+        assert_eq!(decoded.images.len(), 3);
+    }
+
+    #[test]
+    fn frame_limit_is_checked_before_pixel_limit() {
+        // two frames fit within 32 pixels, so stopping at the frame limit never reaches the third
+        let decoded = decode_gif_to_dynamic_images(&test_gif(3), &limits(Some(2), Some(32))).unwrap();
+        assert_eq!(decoded.images.len(), 2);
+    }
+}
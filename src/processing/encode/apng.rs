@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+use image::codecs::gif::Repeat;
+use image::RgbaImage;
+use png::{BitDepth, ColorType};
+
+use crate::core::error::FluxError;
+
+fn convert_repeat(repeat: Repeat) -> u32 {
+    // APNG counts total plays, with 0 meaning forever
+    match repeat {
+        Repeat::Finite(a) => a as u32 + 1,
+        Repeat::Infinite => 0,
+    }
+}
+
+/// Encodes frames, which must all be `width` x `height`, as an animated PNG.
+pub fn encode(
+    frames: Vec<(RgbaImage, Duration)>,
+    width: u32,
+    height: u32,
+    repeat: Repeat,
+) -> Result<Vec<u8>, FluxError> {
+    let mut buf: Vec<u8> = vec![];
+
+    let mut encoder = png::Encoder::new(&mut buf, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, convert_repeat(repeat))
+        .map_err(|e| FluxError::Other(e.to_string()))?;
+
+    let mut writer = encoder.write_header().map_err(|e| FluxError::Other(e.to_string()))?;
+    for (image, delay) in frames {
+        let delay = delay.as_millis().min(u16::MAX as u128) as u16;
+        writer
+            .set_frame_delay(delay, 1000)
+            .map_err(|e| FluxError::Other(e.to_string()))?;
+        writer
+            .write_image_data(image.as_raw())
+            .map_err(|e| FluxError::Other(e.to_string()))?;
+    }
+    writer.finish().map_err(|e| FluxError::Other(e.to_string()))?;
+
+    Ok(buf)
+}
@@ -0,0 +1,38 @@
+use image::codecs::ico::{IcoEncoder, IcoFrame};
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, GenericImageView};
+
+use crate::core::error::FluxError;
+
+/// Largest width and height of an image in an ICO.
+const MAX_SIZE: u32 = 256;
+
+/// Encodes frames as an ICO, with each frame as one of its sizes. Frames larger than 256x256 are
+/// shrunk to fit.
+pub fn encode(frames: Vec<DynamicImage>) -> Result<Vec<u8>, FluxError> {
+    let mut buf: Vec<u8> = vec![];
+
+    let ico_frames = frames
+        .into_iter()
+        .map(|frame| {
+            let (w, h) = frame.dimensions();
+            let frame = if w > MAX_SIZE || h > MAX_SIZE {
+                frame.resize(MAX_SIZE, MAX_SIZE, FilterType::Triangle)
+            } else {
+                frame
+            };
+            let (w, h) = frame.dimensions();
+
+            Ok(IcoFrame::as_png(
+                frame.to_rgba8().as_raw(),
+                w,
+                h,
+                ExtendedColorType::Rgba8,
+            )?)
+        })
+        .collect::<Result<Vec<_>, FluxError>>()?;
+
+    IcoEncoder::new(&mut buf).encode_images(&ico_frames)?;
+
+    Ok(buf)
+}
@@ -1,17 +1,27 @@
 use std::io::Cursor;
 use std::time::Duration;
 
+use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
-use image::{load_from_memory, Delay, ExtendedColorType, GenericImageView, ImageEncoder, ImageFormat};
+use image::imageops::FilterType;
+use image::{
+    load_from_memory, Delay, DynamicImage, ExtendedColorType, GenericImageView, ImageEncoder, ImageFormat, RgbaImage,
+};
 
 use crate::core::error::FluxError;
 use crate::core::media_container::DecodeLimits;
 use crate::processing::decode::dynamic_images::decode_to_dynamic_images;
 use crate::processing::ffmpeg::{self, create_video_from_split};
-use crate::processing::filetype::{get_sig_incl_mp4, Type};
-use crate::processing::media_object::MediaObject;
+use crate::processing::filetype::{get_sig, get_sig_incl_mp4, Type};
+use crate::processing::encode::to_format::video_transcode;
+use crate::processing::media_object::{DynamicImagesMediaObject, MediaObject};
 
+pub mod apng;
 pub mod gif;
+pub mod ico;
+pub mod tiff;
+pub mod to_format;
+pub mod webp;
 
 pub fn encode_auto(obj: MediaObject, limits: &DecodeLimits) -> Result<Vec<u8>, FluxError> {
     let encoded = match obj {
@@ -22,32 +32,17 @@ pub fn encode_auto(obj: MediaObject, limits: &DecodeLimits) -> Result<Vec<u8>, F
                 let inner_images = image_object.images.iter().map(|x| x.0.clone()).collect::<Vec<_>>();
                 create_video_from_split(inner_images, audio, limits)
             } else if image_object.images.len() > 1 {
-                let (w, h) = image_object.maybe_first()?.0.dimensions();
-                let repeat = image_object.repeat;
-
-                let frames = image_object
-                    .into_images()
-                    .into_iter()
-                    .map(|x| (x.0, Delay::from_saturating_duration(x.1.unwrap_or(Duration::default()))))
-                    .collect::<Vec<_>>();
-
-                super::encode::gif::encode(frames, w as u16, h as u16, repeat)
+                encode_gif(image_object)
             } else {
-                let image = &image_object.images.first().unwrap().0;
-                let rgba_image = image.to_rgba8();
-
-                let mut buf: Vec<u8> = Vec::with_capacity(1024usize.pow(2) * 25);
-                let encoder = PngEncoder::new(&mut buf);
-                encoder.write_image(
-                    &rgba_image.into_raw(),
-                    image.width(),
-                    image.height(),
-                    ExtendedColorType::Rgba8,
-                )?;
-
-                Ok(buf)
+                let image = &image_object.maybe_first()?.0;
+                encode_png(image)
             }
         },
+        // formats that can only be read are converted, so that the output can be read back in
+        MediaObject::Encoded(enc) if get_sig(&enc).is_some_and(|ty| ty.is_input_only()) => {
+            let image_object = decode_to_dynamic_images(&enc, limits)?;
+            encode_auto(MediaObject::DynamicImages(image_object), limits)
+        },
         MediaObject::Encoded(enc) => Ok(enc),
     };
 
@@ -56,16 +51,203 @@ pub fn encode_auto(obj: MediaObject, limits: &DecodeLimits) -> Result<Vec<u8>, F
 
 pub fn encode_object(
     obj: MediaObject,
-    format: Option<ImageFormat>,
+    format: Option<Type>,
+    quality: Option<u8>,
     limits: &DecodeLimits,
 ) -> Result<Vec<u8>, FluxError> {
-    if let Some(_f) = format {
-        todo!()
+    if let Some(f) = format {
+        encode_as(obj, f, quality, limits)
+    } else if let MediaObject::Encoded(ref enc) = obj
+        && quality.is_some()
+        && let Some(ty @ (Type::Jpeg | Type::Webp)) = get_sig(enc)
+    {
+        // the automatic choice keeps the input's format, which has a quality to apply
+        let image_object = decode_to_dynamic_images(enc, limits)?;
+        encode_as(MediaObject::DynamicImages(image_object), ty, quality, limits)
     } else {
         encode_auto(obj, limits)
     }
 }
 
+/// Encodes an object to a specific format, re-encoding any already-encoded data if it is not
+/// already of that format. `quality` (1-100) applies to JPEG and WebP, and makes WebP lossy.
+pub fn encode_as(
+    obj: MediaObject,
+    format: Type,
+    quality: Option<u8>,
+    limits: &DecodeLimits,
+) -> Result<Vec<u8>, FluxError> {
+    let image_object = match obj {
+        MediaObject::DynamicImages(image_object) => image_object,
+        MediaObject::Encoded(enc) => match get_sig_incl_mp4(&enc) {
+            Some(ty) if ty == format => return Ok(enc),
+            Some(ty) if ty.is_video() && format.is_video() => return video_transcode(&enc, format),
+            Some(_) => decode_to_dynamic_images(&enc, limits)?,
+            None => {
+                return Err(FluxError::UnsupportedOutputFormat(format!(
+                    "this output cannot be converted to {}",
+                    format.as_str()
+                )));
+            },
+        },
+    };
+
+    // only video can carry audio, and it is never dropped silently
+    if image_object.audio.is_some() && !format.is_video() && !format.is_input_only() {
+        return Err(FluxError::UnsupportedOutputFormat(format!(
+            "{} does not support audio",
+            format.as_str()
+        )));
+    }
+
+    match format {
+        Type::Gif => encode_gif(image_object),
+        Type::Png | Type::Jpeg | Type::Webp | Type::Bmp | Type::Tiff | Type::Ico | Type::Qoi => {
+            if image_object.images.len() > 1 {
+                return match format {
+                    Type::Png => encode_apng(image_object),
+                    Type::Webp => encode_webp(image_object, quality),
+                    Type::Tiff => encode_tiff(image_object),
+                    Type::Ico => encode_ico(image_object),
+                    _ => Err(FluxError::UnsupportedOutputFormat(format!(
+                        "{} does not support animated media",
+                        format.as_str()
+                    ))),
+                };
+            }
+
+            match format {
+                Type::Png => encode_png(&image_object.maybe_first()?.0),
+                Type::Bmp => encode_still(&image_object.maybe_first()?.0, ImageFormat::Bmp),
+                Type::Qoi => encode_still(&image_object.maybe_first()?.0, ImageFormat::Qoi),
+                Type::Tiff => encode_tiff(image_object),
+                Type::Ico => encode_ico(image_object),
+                Type::Jpeg => match quality {
+                    Some(quality) => encode_jpeg(&image_object.maybe_first()?.0, quality),
+                    None => encode_still(&image_object.maybe_first()?.0, ImageFormat::Jpeg),
+                },
+                _ => match quality {
+                    // lossy WebP goes through libwebp, as a single frame animation
+                    Some(_) => encode_webp(image_object, quality),
+                    None => encode_still(&image_object.maybe_first()?.0, ImageFormat::WebP),
+                },
+            }
+        },
+        Type::Svg | Type::Avif | Type::Heic => Err(FluxError::UnsupportedOutputFormat(format!(
+            "{} can only be used as an input",
+            format.as_str()
+        ))),
+        Type::Mp4 | Type::Webm => {
+            let DynamicImagesMediaObject { images, audio, .. } = image_object;
+            let images = images.into_iter().map(|x| x.0).collect::<Vec<_>>();
+            let video = create_video_from_split(images, &audio.unwrap_or_default(), limits)?;
+
+            if format == Type::Webm {
+                video_transcode(&video, format)
+            } else {
+                Ok(video)
+            }
+        },
+    }
+}
+
+fn encode_gif(image_object: DynamicImagesMediaObject) -> Result<Vec<u8>, FluxError> {
+    let (w, h) = image_object.maybe_first()?.0.dimensions();
+    let repeat = image_object.repeat;
+
+    let frames = image_object
+        .into_images()
+        .into_iter()
+        .map(|x| (x.0, Delay::from_saturating_duration(x.1.unwrap_or(Duration::default()))))
+        .collect::<Vec<_>>();
+
+    self::gif::encode(frames, w as u16, h as u16, repeat)
+}
+
+/// Frames of an animation as RGBA, all the size of the first frame, with their delays.
+fn canvas_frames(image_object: DynamicImagesMediaObject) -> Result<(Vec<(RgbaImage, Duration)>, u32, u32), FluxError> {
+    let (w, h) = image_object.maybe_first()?.0.dimensions();
+
+    let frames = image_object
+        .into_images()
+        .into_iter()
+        .map(|x| {
+            let image = if x.0.dimensions() == (w, h) {
+                x.0.into_rgba8()
+            } else {
+                x.0.resize_exact(w, h, FilterType::Nearest).into_rgba8()
+            };
+            (image, x.1.unwrap_or(Duration::default()))
+        })
+        .collect::<Vec<_>>();
+
+    Ok((frames, w, h))
+}
+
+fn encode_apng(image_object: DynamicImagesMediaObject) -> Result<Vec<u8>, FluxError> {
+    let repeat = image_object.repeat;
+    let (frames, w, h) = canvas_frames(image_object)?;
+
+    self::apng::encode(frames, w, h, repeat)
+}
+
+fn encode_webp(image_object: DynamicImagesMediaObject, quality: Option<u8>) -> Result<Vec<u8>, FluxError> {
+    let repeat = image_object.repeat;
+    let (frames, w, h) = canvas_frames(image_object)?;
+
+    self::webp::encode(frames, w, h, repeat, quality)
+}
+
+fn encode_tiff(image_object: DynamicImagesMediaObject) -> Result<Vec<u8>, FluxError> {
+    let frames = image_object
+        .into_images()
+        .into_iter()
+        .map(|x| x.0.into_rgba8())
+        .collect();
+
+    self::tiff::encode(frames)
+}
+
+fn encode_ico(image_object: DynamicImagesMediaObject) -> Result<Vec<u8>, FluxError> {
+    let frames = image_object.into_images().into_iter().map(|x| x.0).collect();
+
+    self::ico::encode(frames)
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>, FluxError> {
+    let rgba_image = image.to_rgba8();
+
+    let mut buf: Vec<u8> = Vec::with_capacity(1024usize.pow(2) * 25);
+    let encoder = PngEncoder::new(&mut buf);
+    encoder.write_image(
+        &rgba_image.into_raw(),
+        image.width(),
+        image.height(),
+        ExtendedColorType::Rgba8,
+    )?;
+
+    Ok(buf)
+}
+
+fn encode_still(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>, FluxError> {
+    let mut out = Vec::new();
+
+    if format == ImageFormat::Jpeg {
+        image.to_rgb8().write_to(&mut Cursor::new(&mut out), format)?;
+    } else {
+        image.to_rgba8().write_to(&mut Cursor::new(&mut out), format)?;
+    }
+
+    Ok(out)
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>, FluxError> {
+    let mut out = Vec::new();
+    JpegEncoder::new_with_quality(&mut out, quality).encode_image(&image.to_rgb8())?;
+
+    Ok(out)
+}
+
 pub fn encode_first_frame_as(
     obj: MediaObject,
     format: ImageFormat,
@@ -93,7 +275,17 @@ pub fn encode_first_frame_as(
         MediaObject::Encoded(enc) => {
             let enc_format = get_sig_incl_mp4(&enc).ok_or(FluxError::UnsupportedFiletype)?;
             match enc_format {
-                Type::Jpeg | Type::Png | Type::Gif | Type::Webp => {
+                Type::Jpeg
+                | Type::Png
+                | Type::Gif
+                | Type::Webp
+                | Type::Bmp
+                | Type::Tiff
+                | Type::Ico
+                | Type::Qoi
+                | Type::Svg
+                | Type::Avif
+                | Type::Heic => {
                     let dyn_images = decode_to_dynamic_images(&enc, limits)?;
                     let frame_1 = dyn_images
                         .images
@@ -126,3 +318,32 @@ pub fn encode_first_frame_as(
 
     Ok(encoded)
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, RgbImage};
+
+    use super::*;
+
+    fn test_jpeg() -> Vec<u8> {
+        let image = RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, (x ^ y) as u8]));
+        encode_jpeg(&DynamicImage::ImageRgb8(image), 100).unwrap()
+    }
+
+    #[test]
+    fn automatic_format_applies_quality() {
+        let jpeg = test_jpeg();
+        let encoded = encode_object(MediaObject::Encoded(jpeg.clone()), None, Some(10), &Default::default()).unwrap();
+
+        assert_eq!(get_sig(&encoded), Some(Type::Jpeg));
+        assert!(encoded.len() < jpeg.len());
+    }
+
+    #[test]
+    fn automatic_format_without_quality_keeps_input() {
+        let jpeg = test_jpeg();
+        let encoded = encode_object(MediaObject::Encoded(jpeg.clone()), None, None, &Default::default()).unwrap();
+
+        assert_eq!(encoded, jpeg);
+    }
+}
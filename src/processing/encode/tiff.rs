@@ -0,0 +1,20 @@
+use std::io::Cursor;
+
+use image::RgbaImage;
+use tiff::encoder::{colortype, TiffEncoder};
+
+use crate::core::error::FluxError;
+
+/// Encodes frames as a TIFF, with each frame as a page.
+pub fn encode(frames: Vec<RgbaImage>) -> Result<Vec<u8>, FluxError> {
+    let mut buf = Cursor::new(vec![]);
+
+    let mut encoder = TiffEncoder::new(&mut buf).map_err(|e| FluxError::Other(e.to_string()))?;
+    for frame in frames {
+        encoder
+            .write_image::<colortype::RGBA8>(frame.width(), frame.height(), frame.as_raw())
+            .map_err(|e| FluxError::Other(e.to_string()))?;
+    }
+
+    Ok(buf.into_inner())
+}
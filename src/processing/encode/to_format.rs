@@ -1,10 +1,26 @@
 use crate::core::error::FluxError;
-use crate::processing::filetype::FileType;
+use crate::processing::ffmpeg::run_ffmpeg_command;
+use crate::processing::filetype::Type;
 
-pub fn video_transcode(buf: &[u8], to: FileType) -> Result<Vec<u8>, FluxError> {
-    if to != FileType::Mp4 && to != FileType::Webm {
-        return Err(FluxError::UnsupportedFiletype);
+pub fn video_transcode(buf: &[u8], to: Type) -> Result<Vec<u8>, FluxError> {
+    match to {
+        Type::Mp4 => run_ffmpeg_command(
+            &[
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-c:a",
+                "aac",
+                "-movflags",
+                "faststart",
+                "-f",
+                "mp4",
+            ],
+            &[],
+            buf,
+        ),
+        Type::Webm => run_ffmpeg_command(&["-c:v", "libvpx-vp9", "-c:a", "libopus", "-f", "webm"], &[], buf),
+        _ => Err(FluxError::UnsupportedFiletype),
     }
-
-    todo!()
 }
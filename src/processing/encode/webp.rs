@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use image::codecs::gif::Repeat;
+use image::RgbaImage;
+
+use crate::core::error::FluxError;
+use crate::vips::vips_webp_save_animated;
+
+fn convert_repeat(repeat: Repeat) -> u16 {
+    // WebP counts total plays, with 0 meaning forever
+    match repeat {
+        Repeat::Finite(a) => a.saturating_add(1),
+        Repeat::Infinite => 0,
+    }
+}
+
+/// Encodes frames, which must all be `width` x `height`, as an animated WebP. The WebP is lossy
+/// if a quality (1-100) is given, and lossless otherwise.
+pub fn encode(
+    frames: Vec<(RgbaImage, Duration)>,
+    width: u32,
+    height: u32,
+    repeat: Repeat,
+    quality: Option<u8>,
+) -> Result<Vec<u8>, FluxError> {
+    let mut delays = Vec::with_capacity(frames.len());
+    let mut stacked = Vec::with_capacity(width as usize * height as usize * 4 * frames.len());
+
+    for (image, delay) in frames {
+        delays.push(delay.as_millis().min(i32::MAX as u128) as i32);
+        stacked.extend_from_slice(image.as_raw());
+    }
+
+    vips_webp_save_animated(&stacked, width, height, &delays, convert_repeat(repeat), quality)
+}
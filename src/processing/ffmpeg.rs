@@ -1,6 +1,5 @@
 use std::collections::hash_map::DefaultHasher;
-use std::convert::Infallible;
-use std::fs::{read, read_dir, remove_dir_all, remove_file, write};
+use std::fs::{read, read_dir, remove_file, write};
 use std::hash::{Hash, Hasher};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
@@ -14,16 +13,19 @@ use rand::prelude::SliceRandom;
 use rand::{Rng, thread_rng};
 use tracing_subscriber::fmt::format;
 
-use super::filetype::{Type, get_sig};
+use super::filetype::{Type, get_sig, get_sig_incl_mp4};
 use crate::core::error::FluxError;
 use crate::core::media_container::DecodeLimits;
+use crate::util::cancel;
 use crate::util::owned_child::IntoOwnedChild;
+use crate::util::progress;
 use crate::util::tmpfile::{TmpFile, TmpFolder};
 use crate::util::{hash_buffer, pad_left};
 
 pub fn run_ffmpeg_command(commands: &[&str], pre_commands: &[&str], input: &[u8]) -> Result<Vec<u8>, FluxError> {
     let cpus = num_cpus::get().to_string();
 
+    // only used to name temporary files, so this does not use the seeded generator
     let rand_string = thread_rng()
         .sample_iter(&Alphanumeric)
         .take(30)
@@ -45,23 +47,47 @@ pub fn run_ffmpeg_command(commands: &[&str], pre_commands: &[&str], input: &[u8]
     }
 
     args.extend_from_slice(commands);
+
+    // ffmpeg writes progress updates to stdout, which are relayed as they arrive. The frame count
+    // of the output is only known ahead of time for video inputs, and only approximately.
+    let show_progress = progress::enabled();
+    if show_progress {
+        args.extend_from_slice(&["-progress", "pipe:1", "-nostats"]);
+    }
+
     args.push(out_file.path());
 
-    let command = Command::new("ffmpeg")
+    let mut command = Command::new("ffmpeg")
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffmpeg");
+
+    let relay = if show_progress && let Some(stdout) = command.stdout.take() {
+        let total = if get_sig_incl_mp4(input).is_some_and(|ty| ty.is_video()) {
+            get_video_frame_count(input).ok().map(|c| c as u64)
+        } else {
+            None
+        };
+
+        Some(spawn(move || progress::relay_ffmpeg_progress(stdout, total)))
+    } else {
+        None
+    };
 
     let exit = command.wait_with_output()?;
+    if let Some(relay) = relay {
+        let _ = relay.join();
+    }
     let code = exit.status;
 
     if !code.success() {
-        return Err(FluxError::ScriptError(
-            String::from_utf8_lossy(&exit.stderr).to_string(),
-        ));
+        return Err(FluxError::ExternalToolError(format!(
+            "ffmpeg: {}",
+            String::from_utf8_lossy(&exit.stderr)
+        )));
     }
 
     let new_buffer = read(out_file.path())?;
@@ -259,22 +285,30 @@ pub fn terraria(input: &[u8]) -> Result<Vec<u8>, FluxError> {
 }
 
 pub fn video_to_dynamic_images(input: &[u8], limits: &DecodeLimits) -> Result<Vec<DynamicImage>, FluxError> {
-    let time_limit = limits
-        .video_time_limit
-        .unwrap_or(Duration::from_secs(45))
-        .as_secs()
-        .to_string();
-    let fps_limit = format!("fps={}", limits.frame_rate_limit.unwrap_or(20));
+    let time_limit = limits.video_time_limit.unwrap_or(Duration::from_secs(45));
+    let fps = limits.frame_rate_limit.unwrap_or(20);
+
+    // the fps filter outputs exactly `fps` frames per second, so the frame count is known upfront
+    if limits.pixel_limit.is_some() {
+        let (width, height) = get_video_dimensions(input)?;
+        let length = get_video_length(input)?.min(time_limit);
+        let frames = (length.as_secs_f64() * fps as f64).ceil() as u64;
+        limits.check_pixel_limit(width as u64, height as u64, frames.max(1))?;
+    }
+
+    let time_limit = time_limit.as_secs_f64().to_string();
+    let fps_limit = format!("fps={fps}");
 
     let cpus = num_cpus::get().to_string();
 
-    let folder_name = hash_buffer(input);
-    std::fs::create_dir(format!("/tmp/{}", folder_name))?;
+    // removed once the frames are read, or on error
+    let folder = TmpFolder::new(hash_buffer(input));
+    std::fs::create_dir(folder.path())?;
 
-    let in_path = format!("/tmp/{}/input", folder_name);
+    let in_path = format!("{}/input", folder.path());
     write(&in_path, input)?;
 
-    let out_path = format!("/tmp/{}/out%05d.bmp", folder_name);
+    let out_path = format!("{}/out%05d.bmp", folder.path());
 
     let mut args = Vec::from(["-y", "-hide_banner", "-loglevel", "error"]);
     args.extend_from_slice(&["-t", &time_limit, "-i", &in_path, "-threads", &cpus]);
@@ -287,58 +321,65 @@ pub fn video_to_dynamic_images(input: &[u8], limits: &DecodeLimits) -> Result<Ve
         .stderr(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffmpeg");
 
     let exit = command.wait_with_output()?;
     let code = exit.status;
 
     if !code.success() {
-        return Err(FluxError::ScriptError(
-            String::from_utf8_lossy(&exit.stderr).to_string(),
-        ));
-    }
-
-    let new_buffers = read_dir(format!("/tmp/{}", folder_name))?;
-    let new_buffers_count = read_dir(format!("/tmp/{}", folder_name))?.count();
-    let mut images: Vec<DynamicImage> = vec![];
-    let mut new_paths_sorted: Vec<String> = vec![String::new(); new_buffers_count];
-
-    for buf in new_buffers {
-        let buf = buf.unwrap();
-        let path = buf.path().to_string_lossy().to_string();
-        if path.ends_with("bmp") {
-            let filename = buf.file_name().to_string_lossy().to_string();
-            let num = filename[3..8].to_string().parse::<usize>().unwrap();
-            new_paths_sorted[num] = path;
-        }
+        return Err(FluxError::ExternalToolError(format!(
+            "ffmpeg: {}",
+            String::from_utf8_lossy(&exit.stderr)
+        )));
     }
 
-    for path in new_paths_sorted {
-        if !path.is_empty() {
-            let file = read(path)?;
-            images.push(DynamicImage::ImageRgba8(load_from_memory(&file)?.into_rgba8()));
+    // frames are numbered in order by ffmpeg, e.g. out00001.bmp
+    let mut frames = vec![];
+    for entry in read_dir(folder.path())? {
+        let entry = entry?;
+        let filename = entry.file_name().to_string_lossy().to_string();
+
+        if let Some(num) = filename.strip_prefix("out").and_then(|f| f.strip_suffix(".bmp")) {
+            let num = num
+                .parse::<usize>()
+                .map_err(|_| FluxError::ScriptError(format!("Unexpected frame {filename} in ffmpeg output")))?;
+            frames.push((num, entry.path()));
         }
     }
+    frames.sort();
 
-    remove_dir_all(format!("/tmp/{}", folder_name))?;
+    let mut images = Vec::with_capacity(frames.len());
+    for (_, path) in frames {
+        let file = read(path)?;
+        images.push(DynamicImage::ImageRgba8(load_from_memory(&file)?.into_rgba8()));
+    }
 
     Ok(images)
 }
 
-pub fn split_video(input: &[u8], limits: DecodeLimits) -> Result<(Vec<DynamicImage>, Vec<u8>), FluxError> {
+/// Decodes a video into its frames and its audio as MP3. The audio is `None` if the video is silent.
+pub fn split_video(input: &[u8], limits: DecodeLimits) -> Result<(Vec<DynamicImage>, Option<Vec<u8>>), FluxError> {
     let boxed = Box::<[u8]>::from(input);
     let arced = Arc::<[u8]>::from(boxed);
     let arced_clone = arced.clone();
 
-    let audio_task = spawn(move || run_ffmpeg_command(&["-f", "mp3"], &[], &arced));
-    let video_task = spawn(move || video_to_dynamic_images(&arced_clone, &limits));
+    // the deadline is per thread, so the caller's is carried over for ffmpeg to be killed when it passes
+    let deadline = cancel::deadline();
+    let audio_task = spawn(move || cancel::with_deadline(deadline, || run_ffmpeg_command(&["-f", "mp3"], &[], &arced)));
+    let video_task = spawn(move || cancel::with_deadline(deadline, || video_to_dynamic_images(&arced_clone, &limits)));
 
-    let imgs = video_task.join().unwrap()?;
-    let audio = audio_task
+    let imgs = video_task
         .join()
-        .unwrap()
-        .or::<Infallible>(Ok(Vec::<u8>::new()))
-        .unwrap();
+        .map_err(|_| FluxError::ScriptError("Video decoding thread panicked".to_owned()))??;
+    let audio = match audio_task
+        .join()
+        .map_err(|_| FluxError::ScriptError("Audio decoding thread panicked".to_owned()))?
+    {
+        Ok(audio) if !audio.is_empty() => Some(audio),
+        Err(FluxError::Timeout) => return Err(FluxError::Timeout),
+        // ffmpeg fails when the input has no audio stream
+        _ => None,
+    };
 
     Ok((imgs, audio))
 }
@@ -388,15 +429,16 @@ pub fn create_video_from_split(
         .stderr(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffmpeg");
 
     let exit = command.wait_with_output()?;
     let code = exit.status;
 
     if !code.success() {
-        return Err(FluxError::ScriptError(
-            String::from_utf8_lossy(&exit.stderr).to_string(),
-        ));
+        return Err(FluxError::ExternalToolError(format!(
+            "ffmpeg: {}",
+            String::from_utf8_lossy(&exit.stderr)
+        )));
     }
 
     let silent_video = read(out_path)?;
@@ -467,18 +509,21 @@ pub fn get_video_dimensions(input: &[u8]) -> Result<(usize, usize), FluxError> {
         .stdout(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffprobe");
 
     let output = command.wait_with_output()?;
     let dimensions = String::from_utf8_lossy(&output.stdout).to_string();
 
-    let parts = dimensions
-        .split('x')
-        .map(|x| x.trim().parse::<usize>().unwrap())
-        .collect::<Vec<_>>();
-
     remove_file(in_path)?;
-    Ok((parts[0], parts[1]))
+
+    let parse = |x: &str| x.trim().parse::<usize>().ok();
+    dimensions
+        .split_once('x')
+        .and_then(|(w, h)| Some((parse(w)?, parse(h)?)))
+        .ok_or(FluxError::CorruptInput(format!(
+            "Could not read video dimensions from ffprobe output: {}",
+            dimensions.trim()
+        )))
 }
 
 pub fn get_video_length(input: &[u8]) -> Result<Duration, FluxError> {
@@ -505,7 +550,7 @@ pub fn get_video_length(input: &[u8]) -> Result<Duration, FluxError> {
         .stdout(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffprobe");
 
     let output = command.wait_with_output()?;
     remove_file(in_path)?;
@@ -548,7 +593,7 @@ pub fn get_video_frame_count(input: &[u8]) -> Result<usize, FluxError> {
         .stdout(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffprobe");
 
     let output = command.wait_with_output()?;
     let count = String::from_utf8_lossy(&output.stdout).to_string();
@@ -595,7 +640,7 @@ pub fn get_video_fps(input: &[u8]) -> Result<f64, FluxError> {
         .stdout(Stdio::piped())
         .args(&args)
         .spawn()?
-        .into_owned_child();
+        .into_owned_child("ffprobe");
 
     let output = command.wait_with_output()?;
     let fps = String::from_utf8_lossy(&output.stdout).to_string();
@@ -846,7 +891,7 @@ pub mod ffmpeg_operations {
             .stdout(Stdio::piped())
             .args(&args)
             .spawn()?
-            .into_owned_child();
+            .into_owned_child("ffmpeg");
 
         c.wait_with_output()?;
 
@@ -877,7 +922,7 @@ pub mod ffmpeg_operations {
         )
     }
 
-    pub fn scramble_video(input: &[u8]) -> Result<Vec<u8>, FluxError> {
+    pub fn scramble_video(input: &[u8], rng: &mut impl Rng) -> Result<Vec<u8>, FluxError> {
         let mut len_remaining = get_video_length(input)
             .context("Failed to get video length")?
             .as_millis();
@@ -885,7 +930,7 @@ pub mod ffmpeg_operations {
         let mut slice_sections = Vec::new();
 
         while len_remaining > 0 {
-            let next_chunk_len = thread_rng().gen_range(1..500).clamp(0, len_remaining) as u128;
+            let next_chunk_len = rng.gen_range(1..500).clamp(0, len_remaining) as u128;
             len_remaining -= next_chunk_len;
             next_start += next_chunk_len;
 
@@ -921,7 +966,7 @@ pub mod ffmpeg_operations {
             Ok::<(), FluxError>(())
         })?;
 
-        files.shuffle(&mut thread_rng());
+        files.shuffle(rng);
 
         let file_list = files
             .iter()
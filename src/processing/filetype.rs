@@ -1,12 +1,24 @@
 use std::cmp::min;
 use std::ops::Range;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Type {
     Gif,
     Jpeg,
     Png,
     Webp,
+    Bmp,
+    /// TIFF, with each page as a frame.
+    Tiff,
+    /// ICO, with each size as a frame.
+    Ico,
+    Qoi,
+    /// SVG, rasterised when decoded. Input only.
+    Svg,
+    /// AVIF, still or animated. Input only.
+    Avif,
+    /// HEIC, or another HEIF image that is not AVIF. Input only.
+    Heic,
     Mp4,
     Webm,
 }
@@ -17,6 +29,13 @@ impl Type {
             Type::Jpeg => "jpeg",
             Type::Png => "png",
             Type::Webp => "webp",
+            Type::Bmp => "bmp",
+            Type::Tiff => "tiff",
+            Type::Ico => "ico",
+            Type::Qoi => "qoi",
+            Type::Svg => "svg",
+            Type::Avif => "avif",
+            Type::Heic => "heic",
             Type::Mp4 => "mp4",
             Type::Webm => "webm",
         }
@@ -27,6 +46,13 @@ impl Type {
             Type::Jpeg => "image/jpeg",
             Type::Png => "image/png",
             Type::Webp => "image/webp",
+            Type::Bmp => "image/bmp",
+            Type::Tiff => "image/tiff",
+            Type::Ico => "image/vnd.microsoft.icon",
+            Type::Qoi => "image/qoi",
+            Type::Svg => "image/svg+xml",
+            Type::Avif => "image/avif",
+            Type::Heic => "image/heic",
             Type::Mp4 => "video/mp4",
             Type::Webm => "video/webm",
         }
@@ -34,12 +60,38 @@ impl Type {
     pub fn is_video(&self) -> bool {
         matches!(self, Type::Mp4 | Type::Webm)
     }
+    /// Whether flux can decode this type but not encode it, so it must be converted before it is
+    /// output or passed to other tools.
+    pub fn is_input_only(&self) -> bool {
+        matches!(self, Type::Svg | Type::Avif | Type::Heic)
+    }
+    /// Maps a file extension (or format name, as accepted by `--format`) to a type.
+    pub fn from_extension(ext: &str) -> Option<Type> {
+        match &ext.to_ascii_lowercase()[..] {
+            "gif" => Some(Type::Gif),
+            "jpg" | "jpeg" => Some(Type::Jpeg),
+            "png" => Some(Type::Png),
+            "webp" => Some(Type::Webp),
+            "bmp" => Some(Type::Bmp),
+            "tif" | "tiff" => Some(Type::Tiff),
+            "ico" => Some(Type::Ico),
+            "qoi" => Some(Type::Qoi),
+            "mp4" => Some(Type::Mp4),
+            "webm" => Some(Type::Webm),
+            _ => None,
+        }
+    }
 }
 
 const GIF: [u8; 3] = [71, 73, 70];
 const JPEG: [u8; 3] = [255, 216, 255];
 const PNG: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
 const WEBP: [u8; 4] = [87, 69, 66, 80];
+const BMP: [u8; 2] = [66, 77];
+const TIFF_LE: [u8; 4] = [73, 73, 42, 0];
+const TIFF_BE: [u8; 4] = [77, 77, 0, 42];
+const ICO: [u8; 4] = [0, 0, 1, 0];
+const QOI: [u8; 4] = [113, 111, 105, 102];
 const MP4: [u8; 4] = [0x66, 0x74, 0x79, 0x70];
 const WEBM: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
 
@@ -51,11 +103,60 @@ fn sig(that: &[u8], eq: &[u8]) -> bool {
     that[0..std::cmp::min(eq.len(), that.len())].eq(eq)
 }
 
+fn check_ico(that: &[u8]) -> bool {
+    // the header is too generic on its own, so the first directory entry must also be plausible
+    let (Some(count), Some(entry)) = (that.get(4..6), that.get(6..22)) else {
+        return false;
+    };
+    let count = u16::from_le_bytes([count[0], count[1]]) as u32;
+    let len = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]);
+    let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]);
+
+    // the reserved byte is always 0, and the image data comes after the directory
+    sig(that, &ICO) && count > 0 && entry[3] == 0 && len > 0 && offset >= 6 + 16 * count
+}
+
+/// Detects SVG from an `<svg` element near the start of the file, after any XML declaration,
+/// doctype or comments.
+fn check_svg(that: &[u8]) -> bool {
+    let start = &that[bounded_range(0, 4096, that.len())];
+    let text = String::from_utf8_lossy(start);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    ["<svg", "<?xml", "<!"].iter().any(|p| text.starts_with(p)) && text.contains("<svg")
+}
+
 fn check_webp(that: &[u8]) -> bool {
     let bytes_offset_removed = &that[bounded_range(8, 12, that.len())];
     sig(bytes_offset_removed, &WEBP)
 }
 
+/// Brands of the `ftyp` box that mark a HEIF image (or image sequence) rather than a video.
+const AVIF_BRANDS: &[&[u8; 4]] = &[b"avif", b"avis"];
+const HEIF_BRANDS: &[&[u8; 4]] = &[b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"mif1", b"msf1"];
+
+/// Detects AVIF and HEIC from the brands of the `ftyp` box, which they share with MP4.
+fn check_heif(that: &[u8]) -> Option<Type> {
+    if !check_mp4(that) {
+        return None;
+    }
+
+    let size = u32::from_be_bytes(that[0..4].try_into().ok()?) as usize;
+    // the major brand, then (after a 4 byte minor version) the compatible brands
+    let major = that.get(8..12)?;
+    let compatible = that.get(16..min(size, that.len())).unwrap_or_default().chunks_exact(4);
+    let mut brands = std::iter::once(major).chain(compatible);
+
+    if brands.any(|b| AVIF_BRANDS.iter().any(|a| &a[..] == b)) {
+        Some(Type::Avif)
+    } else if HEIF_BRANDS.iter().any(|h| &h[..] == major) {
+        // generic brands such as mif1 also appear in some MP4s, so only trust the major brand
+        Some(Type::Heic)
+    } else {
+        None
+    }
+}
+
 fn check_mp4(that: &[u8]) -> bool {
     let bytes_offset_removed = &that[bounded_range(4, 8, that.len())];
     sig(bytes_offset_removed, &MP4)
@@ -73,6 +174,19 @@ pub fn get_sig(buf: &[u8]) -> Option<Type> {
         Some(Type::Png)
     } else if check_webp(buf) {
         Some(Type::Webp)
+    } else if sig(buf, &TIFF_LE) || sig(buf, &TIFF_BE) {
+        Some(Type::Tiff)
+    } else if sig(buf, &QOI) {
+        Some(Type::Qoi)
+    } else if check_mp4(buf) {
+        // checked before ICO, since an ftyp box of 256 bytes starts with the ICO header
+        check_heif(buf)
+    } else if check_ico(buf) {
+        Some(Type::Ico)
+    } else if sig(buf, &BMP) {
+        Some(Type::Bmp)
+    } else if check_svg(buf) {
+        Some(Type::Svg)
     } else {
         None
     }
@@ -90,11 +204,69 @@ pub fn get_sig_incl_mp4(buf: &[u8]) -> Option<Type> {
         Some(Type::Png)
     } else if check_webp(buf) {
         Some(Type::Webp)
+    } else if sig(buf, &TIFF_LE) || sig(buf, &TIFF_BE) {
+        Some(Type::Tiff)
+    } else if sig(buf, &QOI) {
+        Some(Type::Qoi)
     } else if check_mp4(buf) {
-        Some(Type::Mp4)
+        // checked before ICO, since an ftyp box of 256 bytes starts with the ICO header
+        check_heif(buf).or(Some(Type::Mp4))
+    } else if check_ico(buf) {
+        Some(Type::Ico)
+    } else if sig(buf, &BMP) {
+        Some(Type::Bmp)
+    } else if check_svg(buf) {
+        Some(Type::Svg)
     } else if sig(buf, &WEBM) {
         Some(Type::Webm)
     } else {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ftyp(size: u32, major: &[u8; 4]) -> Vec<u8> {
+        let mut buf = size.to_be_bytes().to_vec();
+        buf.extend_from_slice(b"ftyp");
+        buf.extend_from_slice(major);
+        buf.resize(size as usize, 0);
+        buf
+    }
+
+    fn ico(count: u16, len: u32, offset: u32) -> Vec<u8> {
+        let mut buf = vec![0, 0, 1, 0];
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf.extend_from_slice(&[16, 16, 0, 0, 1, 0, 32, 0]);
+        buf.extend_from_slice(&len.to_le_bytes());
+        buf.extend_from_slice(&offset.to_le_bytes());
+        buf.resize(offset as usize + len as usize, 0);
+        buf
+    }
+
+    #[test]
+    fn mp4_with_256_byte_ftyp_is_not_ico() {
+        let mp4 = ftyp(256, b"isom");
+        assert!(mp4.starts_with(&ICO));
+        assert_eq!(get_sig_incl_mp4(&mp4), Some(Type::Mp4));
+        assert_eq!(get_sig(&mp4), None);
+
+        assert_eq!(get_sig(&ftyp(256, b"avif")), Some(Type::Avif));
+    }
+
+    #[test]
+    fn detects_ico() {
+        assert_eq!(get_sig(&ico(1, 40, 22)), Some(Type::Ico));
+        assert_eq!(get_sig_incl_mp4(&ico(1, 40, 22)), Some(Type::Ico));
+    }
+
+    #[test]
+    fn rejects_implausible_ico() {
+        // no images, empty image data, and image data overlapping the directory
+        assert_eq!(get_sig(&ico(0, 40, 22)), None);
+        assert_eq!(get_sig(&ico(1, 0, 22)), None);
+        assert_eq!(get_sig(&ico(2, 40, 22)), None);
+    }
+}
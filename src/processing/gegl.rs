@@ -1,17 +1,19 @@
 use std::io::Cursor;
+use std::process::Stdio;
 
 use image::{DynamicImage, ImageFormat};
 
+use crate::core::error::FluxError;
 use crate::util::hash_buffer;
 use crate::util::owned_child::IntoOwnedChild;
 use crate::util::tmpfile::TmpFile;
 
-fn gegl(image: &DynamicImage, args: &[&str]) -> DynamicImage {
+fn gegl(image: &DynamicImage, args: &[&str]) -> Result<DynamicImage, FluxError> {
     let mut frame: Vec<u8> = Vec::new();
-    image.write_to(&mut Cursor::new(&mut frame), ImageFormat::Png).unwrap();
+    image.write_to(&mut Cursor::new(&mut frame), ImageFormat::Png)?;
     let hash = hash_buffer(&frame);
     let file = TmpFile::new(format!("gegl-{}.png", hash));
-    file.write(frame).unwrap();
+    file.write(frame)?;
 
     let args = args.to_vec();
 
@@ -22,25 +24,36 @@ fn gegl(image: &DynamicImage, args: &[&str]) -> DynamicImage {
         .arg(file.path())
         .arg("--")
         .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
-        .unwrap()
-        .into_owned_child();
+        .map_err(|e| FluxError::ExternalToolError(format!("Failed to execute gegl: {e}")))?
+        .into_owned_child("gegl");
 
-    let output = gegl_process.wait_with_output().unwrap();
-    let file = std::fs::read(file.path()).unwrap();
+    // a timeout is returned as `FluxError::Timeout`
+    let output = gegl_process.wait_with_output()?;
 
-    if !output.stderr.is_empty() {
-        panic!("{}", String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        return Err(FluxError::ExternalToolError(format!(
+            "gegl: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
-    image::load_from_memory(&file).unwrap()
+    let file = std::fs::read(file.path())?;
+    Ok(image::load_from_memory(&file)?)
 }
 
-pub fn zoom_blur(image: &DynamicImage, factor: f32) -> DynamicImage {
+pub fn zoom_blur(image: &DynamicImage, factor: f32) -> Result<DynamicImage, FluxError> {
     gegl(image, &["motion-blur-zoom", &format!("factor={factor}")])
 }
 
-pub fn softglow(image: &DynamicImage, radius: usize, brightness: usize, sharpness: usize) -> DynamicImage {
+pub fn softglow(
+    image: &DynamicImage,
+    radius: usize,
+    brightness: usize,
+    sharpness: usize,
+) -> Result<DynamicImage, FluxError> {
     gegl(
         image,
         &[
@@ -52,21 +65,21 @@ pub fn softglow(image: &DynamicImage, radius: usize, brightness: usize, sharpnes
     )
 }
 
-pub fn fisheye(image: &DynamicImage) -> DynamicImage {
+pub fn fisheye(image: &DynamicImage) -> Result<DynamicImage, FluxError> {
     gegl(
         image,
         &["lens-distortion", "main=95", "edge=100", "zoom=100", "brighten=20"],
     )
 }
 
-pub fn paint(image: &DynamicImage) -> DynamicImage {
+pub fn paint(image: &DynamicImage) -> Result<DynamicImage, FluxError> {
     gegl(image, &["waterpixels"])
 }
 
-pub fn neon(image: &DynamicImage, factor: f64) -> DynamicImage {
+pub fn neon(image: &DynamicImage, factor: f64) -> Result<DynamicImage, FluxError> {
     gegl(image, &["edge-neon", &format!("radius={}", factor * 2.0)])
 }
 
-pub fn globe(image: &DynamicImage) -> DynamicImage {
+pub fn globe(image: &DynamicImage) -> Result<DynamicImage, FluxError> {
     gegl(image, &["apply-lens", "refraction-index=2.5"])
 }
@@ -0,0 +1,46 @@
+/// One image in an ICO file.
+pub struct IcoEntry {
+    pub width: u32,
+    pub height: u32,
+    /// The entry as an ICO file of its own, so that it can be decoded on its own.
+    pub data: Vec<u8>,
+}
+
+/// Splits an ICO file into its images, largest first. Entries that point outside of the file are
+/// skipped.
+pub fn ico_get_entries(buf: &[u8]) -> Vec<IcoEntry> {
+    const HEADER_LEN: usize = 6;
+    const ENTRY_LEN: usize = 16;
+
+    let count = buf.get(4..6).map(|c| u16::from_le_bytes([c[0], c[1]])).unwrap_or(0) as usize;
+    let mut entries = vec![];
+
+    for i in 0..count {
+        let start = HEADER_LEN + i * ENTRY_LEN;
+        let Some(entry) = buf.get(start..start + ENTRY_LEN) else {
+            break;
+        };
+
+        // a size of 0 means 256
+        let width = if entry[0] == 0 { 256 } else { entry[0] as u32 };
+        let height = if entry[1] == 0 { 256 } else { entry[1] as u32 };
+        let len = u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]) as usize;
+        let offset = u32::from_le_bytes([entry[12], entry[13], entry[14], entry[15]]) as usize;
+        let Some(image) = buf.get(offset..offset.saturating_add(len)) else {
+            continue;
+        };
+
+        // the same header with a count of 1, then this entry pointing just past it
+        let mut data = Vec::with_capacity(HEADER_LEN + ENTRY_LEN + len);
+        data.extend_from_slice(&buf[0..4]);
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&entry[0..12]);
+        data.extend_from_slice(&((HEADER_LEN + ENTRY_LEN) as u32).to_le_bytes());
+        data.extend_from_slice(image);
+
+        entries.push(IcoEntry { width, height, data });
+    }
+
+    entries.sort_by_key(|e| std::cmp::Reverse(e.width as u64 * e.height as u64));
+    entries
+}
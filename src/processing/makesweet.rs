@@ -1,10 +1,12 @@
 use std::fs;
-use std::process::Command;
+use std::io::ErrorKind;
+use std::process::{Command, Stdio};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 
 use crate::core::error::FluxError;
+use crate::util::owned_child::IntoOwnedChild;
 
 struct MakesweetDirectoryDeletionDefer<'a>(&'a str);
 impl<'a> Drop for MakesweetDirectoryDeletionDefer<'a> {
@@ -41,8 +43,19 @@ pub fn run_makesweet(images: &[&[u8]], operation: &str) -> Result<Vec<u8>, FluxE
     let mut command = Command::new("docker");
     command.current_dir("./makesweet");
 
+    // named so that the container can be stopped if we time out, since killing the docker client
+    // does not stop it
+    let container_name = format!("flux-{relative_dir}");
     let share_path = format!("{dir}:/share");
-    command.args(&["run", "--rm", "-v", &share_path, "paulfitz/makesweet"]);
+    command.args(&[
+        "run",
+        "--rm",
+        "--name",
+        &container_name,
+        "-v",
+        &share_path,
+        "paulfitz/makesweet",
+    ]);
 
     let template = format!("{operation}.zip");
     command.args(&["--zip", &template]);
@@ -56,14 +69,25 @@ pub fn run_makesweet(images: &[&[u8]], operation: &str) -> Result<Vec<u8>, FluxE
     command.args(&["--gif", output_path]);
 
     let output = command
-        .output()
-        .map_err(|e| FluxError::ScriptError(format!("Failed to execute Makesweet: {e}")))?;
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| FluxError::ExternalToolError(format!("Failed to execute Makesweet: {e}")))?
+        .into_owned_child("makesweet")
+        .wait_with_output();
+
+    if let Err(e) = &output
+        && e.kind() == ErrorKind::TimedOut
+    {
+        let _ = Command::new("docker").args(&["kill", &container_name]).output();
+    }
+    let output = output?;
 
     if output.status.success() {
         let output = fs::read(format!("{dir}/{output_path}")).context("Failed to read Makesweet output")?;
         Ok(output)
     } else {
-        Err(FluxError::ScriptError(format!(
+        Err(FluxError::ExternalToolError(format!(
             "Error performing Makesweet operation: {}",
             String::from_utf8_lossy(&output.stderr)
         )))
@@ -7,7 +7,8 @@ use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallel
 
 use crate::core::error::FluxError;
 use crate::processing::dynamic_image_wrapper::DynamicImageWrapper;
-use crate::util::collapse_neg;
+use crate::util::progress::FrameCounter;
+use crate::util::{cancel, collapse_neg};
 
 #[derive(Clone)]
 pub struct DynamicImagesMediaObject {
@@ -16,14 +17,22 @@ pub struct DynamicImagesMediaObject {
     pub repeat: Repeat,
 }
 impl DynamicImagesMediaObject {
+    /// Maps every frame in parallel, reporting progress as each frame finishes. Once the current
+    /// deadline passes, remaining frames are left unchanged, and the timeout is reported by
+    /// `MediaContainer::handle_operation`.
     pub fn iter_images_mut<T: Fn(&mut DynamicImage, usize) -> DynamicImage + Send + Sync>(
         &mut self,
         func: T,
     ) -> &mut Self {
-        self.images
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(i, img)| img.0 = func(&mut img.0, i));
+        let deadline = cancel::deadline();
+        let counter = FrameCounter::new(Some(self.images.len() as u64));
+
+        self.images.par_iter_mut().enumerate().for_each(|(i, img)| {
+            if !cancel::expired(deadline) {
+                img.0 = cancel::with_deadline(deadline, || func(&mut img.0, i));
+                counter.tick();
+            }
+        });
 
         self
     }
@@ -34,15 +43,26 @@ impl DynamicImagesMediaObject {
         &mut self,
         func: T,
     ) -> Result<&mut Self, FluxError> {
-        self.images.par_iter_mut().enumerate().try_for_each(
-            |(i, img): (usize, &mut DynamicImageWrapper)| match func(&mut img.0, i) {
-                Ok(r) => {
-                    img.0 = r;
-                    return Ok(());
-                },
-                Err(e) => return Err(e),
-            },
-        )?;
+        let deadline = cancel::deadline();
+        let counter = FrameCounter::new(Some(self.images.len() as u64));
+
+        self.images
+            .par_iter_mut()
+            .enumerate()
+            .try_for_each(|(i, img): (usize, &mut DynamicImageWrapper)| {
+                if cancel::expired(deadline) {
+                    return Err(FluxError::Timeout);
+                }
+
+                match cancel::with_deadline(deadline, || func(&mut img.0, i)) {
+                    Ok(r) => {
+                        img.0 = r;
+                        counter.tick();
+                        return Ok(());
+                    },
+                    Err(e) => return Err(e),
+                }
+            })?;
 
         Ok(self)
     }
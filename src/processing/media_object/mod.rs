@@ -12,6 +12,7 @@ use image::ImageFormat;
 
 use super::encode::encode_first_frame_as;
 
+#[derive(Clone)]
 pub enum MediaObject {
     Encoded(Vec<u8>),
     DynamicImages(DynamicImagesMediaObject),
@@ -24,13 +25,15 @@ impl MediaObject {
         }
     }
 
-    pub fn try_encoded_video(&self, decode_permitted: bool) -> Option<Result<&[u8], FluxError>> {
+    pub fn try_encoded_video(&self, limits: &DecodeLimits) -> Option<Result<&[u8], FluxError>> {
         match self {
             Self::DynamicImages(_) => None,
             Self::Encoded(enc) => {
                 if self.is_encoded_video() {
-                    if !decode_permitted {
-                        return Some(Err(FluxError::VideoDecodeDisabled));
+                    if !limits.video_decode_permitted {
+                        return Some(Err(FluxError::VideoDecodeDisabled(
+                            limits.video_decode_disabled_message(),
+                        )));
                     } else {
                         Some(Ok(enc))
                     }
@@ -60,7 +63,18 @@ impl MediaObject {
     }
 
     pub fn encode(self, limits: &DecodeLimits) -> Result<Vec<u8>, FluxError> {
-        encode_object(self, None, limits)
+        encode_object(self, None, None, limits)
+    }
+
+    /// Encodes this object as `format`, or picks a format automatically if none is specified.
+    /// `quality` applies to lossy formats.
+    pub fn encode_as(
+        self,
+        format: Option<Type>,
+        quality: Option<u8>,
+        limits: &DecodeLimits,
+    ) -> Result<Vec<u8>, FluxError> {
+        encode_object(self, format, quality, limits)
     }
 
     pub fn encode_first_frame_as(self, format: ImageFormat, limits: &DecodeLimits) -> Result<Vec<u8>, FluxError> {
@@ -19,9 +19,11 @@ pub mod filetype;
 pub mod framebuffer;
 pub mod gegl;
 pub mod gif;
+pub mod ico;
 pub mod makesweet;
 pub mod media_object;
 pub mod type_conversion;
+pub mod webp;
 
 pub fn roll_image(image: &DynamicImage, x_scroll: u32, y_scroll: u32) -> DynamicImage {
     let mut canvas = DynamicImage::new_rgba8(image.width(), image.height());
@@ -38,11 +40,10 @@ pub fn roll_image(image: &DynamicImage, x_scroll: u32, y_scroll: u32) -> Dynamic
     canvas
 }
 
-pub fn spread_image(image: &mut DynamicImage, spread_size: usize) {
+pub fn spread_image(image: &mut DynamicImage, spread_size: usize, rng: &mut impl Rng) {
     let w = image.width() as isize;
     let h = image.height() as isize;
     let spread_size = spread_size as isize;
-    let mut rng = rand::thread_rng();
     for (x, y, px) in image.pixels().collect::<Vec<_>>() {
         let displacement_x = if spread_size == 0 {
             0
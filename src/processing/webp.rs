@@ -0,0 +1,45 @@
+use image::codecs::gif::Repeat;
+
+/// Iterates over the top-level chunks of a WebP file as `(fourcc, data)`, stopping at the first
+/// truncated chunk.
+fn webp_chunks(buf: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    // skip "RIFF", the file size and "WEBP"
+    let mut rest = buf.get(12..).unwrap_or_default();
+
+    std::iter::from_fn(move || {
+        let fourcc = rest.get(0..4)?;
+        let len = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?) as usize;
+        let data = rest.get(8..8 + len)?;
+        // chunks are padded to an even length
+        rest = rest.get(8 + len + (len & 1)..).unwrap_or_default();
+
+        Some((fourcc, data))
+    })
+}
+
+/// Whether the WebP is animated, even if it only has one frame.
+pub fn webp_is_animated(buf: &[u8]) -> bool {
+    webp_chunks(buf).any(|(fourcc, _)| fourcc == b"ANIM")
+}
+
+/// The number of frames in a WebP, without decoding any of them. Still images have one frame.
+pub fn webp_get_frame_count(buf: &[u8]) -> u64 {
+    let frames = webp_chunks(buf).filter(|(fourcc, _)| *fourcc == b"ANMF").count() as u64;
+    frames.max(1)
+}
+
+pub fn webp_get_repeat_count(buf: &[u8]) -> Repeat {
+    // the ANIM chunk is a 4 byte background colour followed by a 2 byte loop count
+    let loops = webp_chunks(buf)
+        .find(|(fourcc, _)| *fourcc == b"ANIM")
+        .and_then(|(_, data)| data.get(4..6))
+        .map(|loops| u16::from_le_bytes([loops[0], loops[1]]))
+        .unwrap_or(0);
+
+    // WebP counts total plays, whereas `Repeat` counts plays after the first, as GIF does
+    if loops == 0 {
+        Repeat::Infinite
+    } else {
+        Repeat::Finite(loops - 1)
+    }
+}
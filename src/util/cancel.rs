@@ -0,0 +1,69 @@
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::core::error::FluxError;
+
+/// Deadline for the whole process, set by `--timeout`.
+static GLOBAL_DEADLINE: Mutex<Option<Instant>> = Mutex::new(None);
+
+thread_local! {
+    /// Deadline for the work running on this thread, e.g. a single operation or server job.
+    static LOCAL_DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+pub fn set_global_deadline(deadline: Option<Instant>) {
+    *GLOBAL_DEADLINE.lock().unwrap_or_else(|e| e.into_inner()) = deadline;
+}
+
+pub fn global_deadline() -> Option<Instant> {
+    *GLOBAL_DEADLINE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The earliest deadline that applies to the current thread, if any.
+pub fn deadline() -> Option<Instant> {
+    match (global_deadline(), LOCAL_DEADLINE.get()) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+pub fn expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|d| Instant::now() >= d)
+}
+
+/// Returns a timeout error if the current thread's deadline has passed.
+pub fn check() -> Result<(), FluxError> {
+    if expired(deadline()) {
+        return Err(FluxError::Timeout);
+    }
+
+    Ok(())
+}
+
+/// Runs `f` on this thread with the given deadline in place of this thread's current deadline.
+/// This is used to carry a deadline over to other threads, such as rayon workers, which may be
+/// running unrelated work with its own deadline.
+pub fn with_deadline<T>(deadline: Option<Instant>, f: impl FnOnce() -> T) -> T {
+    // restores the previous deadline even if `f` panics, since the thread may be reused
+    struct Restore(Option<Instant>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            LOCAL_DEADLINE.set(self.0);
+        }
+    }
+
+    let _restore = Restore(LOCAL_DEADLINE.replace(deadline));
+    f()
+}
+
+/// Runs `f` on this thread, timing out after `timeout` if given, or earlier if this thread already
+/// has an earlier deadline.
+pub fn with_timeout<T>(timeout: Option<Duration>, f: impl FnOnce() -> T) -> T {
+    let deadline = match (LOCAL_DEADLINE.get(), timeout.map(|t| Instant::now() + t)) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    with_deadline(deadline, f)
+}
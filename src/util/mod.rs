@@ -1,12 +1,53 @@
+use std::fs;
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process::Command;
 
 use rand::Rng;
 
+use crate::core::error::FluxError;
 use crate::processing::dynamic_image_wrapper::DynamicImageWrapper;
 
+pub mod cancel;
 pub mod owned_child;
+pub mod profile;
+pub mod progress;
 pub mod tmpfile;
 
+/// Kills every child process of this process (e.g. ffmpeg) and removes its temporary files. Used
+/// for graceful shutdown.
+pub fn kill_children_and_remove_tmp_files() -> std::io::Result<()> {
+    let current_pid = std::process::id();
+
+    Command::new("pkill").arg("-P").arg(current_pid.to_string()).output()?;
+
+    for file in fs::read_dir("/tmp")? {
+        if let Ok(f) = file
+            && let Some(name) = f.file_name().to_str()
+            && name.starts_with(&format!("{current_pid}-"))
+        {
+            let _ = fs::remove_file(format!("/tmp/{name}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `f`, converting a panic into a `FluxError::ScriptError` that describes `context`, so that
+/// a bug in one operation is reported like any other error. Temporary files and child processes
+/// are cleaned up as the panic unwinds.
+pub fn catch_panic<T>(context: &str, f: impl FnOnce() -> Result<T, FluxError>) -> Result<T, FluxError> {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or("unknown panic".to_owned());
+
+        Err(FluxError::ScriptError(format!("{context} panicked: {message}")))
+    })
+}
+
 pub fn pad_left(s: String, m: usize, c: char) -> String {
     if let Some(columns_short) = m.checked_sub(s.len()) {
         let padding_width = 1;
@@ -28,6 +69,23 @@ pub fn hash_buffer(buf: &[u8]) -> String {
     format!("{:x}{:x}", body_hasher.finish(), rand)
 }
 
+/// Levenshtein distance between two strings.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == *cb { 0 } else { 1 };
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        prev = current;
+    }
+
+    prev[b.len()]
+}
+
 pub fn convert_ratio_to_integer(numer: u32, denom: u32) -> u32 {
     let ratio = num_rational::Ratio::from_integer(numer / denom);
     let out = ratio.numer();
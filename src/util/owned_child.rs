@@ -1,16 +1,71 @@
-use std::io;
+use std::io::{self, ErrorKind, Read};
 use std::ops::{Deref, DerefMut};
 use std::process::{Child, Output};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub struct OwnedChild(Option<Child>);
+use crate::util::{cancel, profile};
+
+/// A child process that is killed when dropped. `tool` names the program (e.g. `ffmpeg`) for
+/// profiling.
+pub struct OwnedChild(Option<Child>, &'static str, Instant);
 
 impl OwnedChild {
-    pub fn new(c: Child) -> Self {
-        Self(Some(c))
+    pub fn new(c: Child, tool: &'static str) -> Self {
+        Self(Some(c), tool, Instant::now())
+    }
+
+    fn record_time(&self) {
+        profile::record_external(self.1, self.2.elapsed());
     }
 
+    /// Waits for the child to exit and collects its output. If the current thread has a deadline
+    /// (see `util::cancel`) and it passes first, the child is killed and a `TimedOut` error is
+    /// returned.
     pub fn wait_with_output(mut self) -> io::Result<Output> {
-        self.0.take().unwrap().wait_with_output()
+        let Some(deadline) = cancel::deadline() else {
+            let output = self.0.take().unwrap().wait_with_output();
+            self.record_time();
+            return output;
+        };
+
+        let mut child = self.0.take().unwrap();
+        drop(child.stdin.take());
+
+        // read the pipes on other threads so that a full pipe cannot block the child while we wait
+        fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+            thread::spawn(move || {
+                let mut buf = vec![];
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                buf
+            })
+        }
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                self.record_time();
+                break status;
+            }
+
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                self.record_time();
+                return Err(io::Error::new(ErrorKind::TimedOut, "child process timed out"));
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
     }
 }
 
@@ -35,16 +90,17 @@ impl Drop for OwnedChild {
         if let Some(this) = &mut self.0 {
             let _ = this.kill();
             let _ = this.wait();
+            self.record_time();
         }
     }
 }
 
 pub trait IntoOwnedChild {
-    fn into_owned_child(self) -> OwnedChild;
+    fn into_owned_child(self, tool: &'static str) -> OwnedChild;
 }
 
 impl IntoOwnedChild for Child {
-    fn into_owned_child(self) -> OwnedChild {
-        OwnedChild::new(self)
+    fn into_owned_child(self, tool: &'static str) -> OwnedChild {
+        OwnedChild::new(self, tool)
     }
 }
@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::processing::filetype::get_sig_incl_mp4;
+use crate::processing::media_object::{DynamicImagesMediaObject, MediaObject};
+
+/// Profile of the current process, set up by `--profile`. This is global rather than per
+/// `MediaContainer` because decoding and external tools run on other threads (e.g. rayon workers),
+/// so it is only accurate when one pipeline runs at a time, as on the command line.
+static PROFILE: Mutex<Option<Profile>> = Mutex::new(None);
+
+/// Frame count and dimensions (of the first frame) of decoded media.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct MediaStats {
+    pub frames: usize,
+    pub width: u32,
+    pub height: u32,
+}
+impl MediaStats {
+    /// Stats for `object` if it is decoded. Encoded media is not decoded just to find its stats.
+    pub fn of(object: &MediaObject) -> Option<Self> {
+        match object {
+            MediaObject::DynamicImages(images) => Self::of_images(images),
+            MediaObject::Encoded(_) => None,
+        }
+    }
+
+    pub fn of_images(images: &DynamicImagesMediaObject) -> Option<Self> {
+        let first = &images.images.first()?.0;
+
+        Some(Self {
+            frames: images.images.len(),
+            width: first.width(),
+            height: first.height(),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DecodeProfile {
+    pub ms: f64,
+    #[serde(flatten)]
+    pub stats: Option<MediaStats>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OperationProfile {
+    pub name: String,
+    /// Wall time of the whole operation, including decoding and external tools.
+    pub ms: f64,
+    /// Time spent decoding inputs during the operation.
+    pub decode_ms: f64,
+    /// Time spent in external tools during the operation.
+    pub external_ms: f64,
+    /// Stats for each input, or `null` if the input was never decoded (e.g. videos passed straight
+    /// to ffmpeg).
+    pub inputs: Vec<Option<MediaStats>>,
+    pub output: Option<MediaStats>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EncodeProfile {
+    /// The format that was encoded, if it could be detected.
+    pub format: Option<&'static str>,
+    pub ms: f64,
+    pub bytes: usize,
+    pub input: Option<MediaStats>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ProfileReport {
+    pub total_ms: f64,
+    pub decode_ms: f64,
+    pub encode_ms: f64,
+    /// Total time spent in each external tool, e.g. ffmpeg, gegl or vips.
+    pub external_ms: BTreeMap<&'static str, f64>,
+    /// Peak resident set size of flux itself, in KiB.
+    pub peak_rss_kb: u64,
+    /// Peak resident set size of the largest child process (e.g. ffmpeg), in KiB.
+    pub peak_child_rss_kb: u64,
+    pub decodes: Vec<DecodeProfile>,
+    pub operations: Vec<OperationProfile>,
+    pub encodes: Vec<EncodeProfile>,
+}
+
+struct Profile {
+    start: Instant,
+    decode_time: Duration,
+    external_time: BTreeMap<&'static str, Duration>,
+    decodes: Vec<DecodeProfile>,
+    operations: Vec<OperationProfile>,
+    encodes: Vec<EncodeProfile>,
+}
+impl Profile {
+    fn external_total(&self) -> Duration {
+        self.external_time.values().sum()
+    }
+}
+
+/// Where the profile was at when an operation started, so that the decoding and external tool time
+/// of that operation can be worked out once it finishes.
+pub struct OperationMarker {
+    start: Instant,
+    decodes: usize,
+    decode_time: Duration,
+    external_time: Duration,
+    inputs: Vec<Option<MediaStats>>,
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn with_profile(f: impl FnOnce(&mut Profile)) {
+    if let Some(profile) = PROFILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        f(profile);
+    }
+}
+
+/// Starts profiling. Does nothing if profiling has already started.
+pub fn enable() {
+    let mut profile = PROFILE.lock().unwrap_or_else(|e| e.into_inner());
+    if profile.is_none() {
+        *profile = Some(Profile {
+            start: Instant::now(),
+            decode_time: Duration::ZERO,
+            external_time: BTreeMap::new(),
+            decodes: vec![],
+            operations: vec![],
+            encodes: vec![],
+        });
+    }
+}
+
+pub fn record_decode(duration: Duration, stats: Option<MediaStats>) {
+    with_profile(|p| {
+        p.decode_time += duration;
+        p.decodes.push(DecodeProfile {
+            ms: ms(duration),
+            stats,
+        });
+    });
+}
+
+pub fn record_external(tool: &'static str, duration: Duration) {
+    with_profile(|p| *p.external_time.entry(tool).or_default() += duration);
+}
+
+/// Runs `f`, counting the time it takes towards `tool`. Child processes are timed by `OwnedChild`
+/// instead.
+pub fn time_external<T>(tool: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record_external(tool, start.elapsed());
+
+    result
+}
+
+/// Marks the start of an operation with the stats of its inputs, if profiling.
+pub fn start_operation(inputs: impl FnOnce() -> Vec<Option<MediaStats>>) -> Option<OperationMarker> {
+    let profile = PROFILE.lock().unwrap_or_else(|e| e.into_inner());
+    let profile = profile.as_ref()?;
+
+    Some(OperationMarker {
+        start: Instant::now(),
+        decodes: profile.decodes.len(),
+        decode_time: profile.decode_time,
+        external_time: profile.external_total(),
+        inputs: inputs(),
+    })
+}
+
+pub fn finish_operation(marker: Option<OperationMarker>, name: &str, output: Option<MediaStats>) {
+    let Some(mut marker) = marker else {
+        return;
+    };
+    let elapsed = marker.start.elapsed();
+
+    with_profile(|p| {
+        // inputs are decoded lazily by the operation itself, in order, so inputs that were still
+        // encoded when it started take their stats from the decodes that happened since
+        let mut decoded = p.decodes[marker.decodes..].iter().map(|d| d.stats);
+        for input in marker.inputs.iter_mut().filter(|i| i.is_none()) {
+            *input = decoded.next().flatten();
+        }
+
+        p.operations.push(OperationProfile {
+            name: name.to_owned(),
+            ms: ms(elapsed),
+            decode_ms: ms(p.decode_time - marker.decode_time),
+            external_ms: ms(p.external_total() - marker.external_time),
+            inputs: marker.inputs,
+            output,
+        });
+    });
+}
+
+pub fn record_encode(duration: Duration, output: &[u8], input: Option<MediaStats>) {
+    with_profile(|p| {
+        p.encodes.push(EncodeProfile {
+            format: get_sig_incl_mp4(output).map(|ty| ty.as_str()),
+            ms: ms(duration),
+            bytes: output.len(),
+            input,
+        })
+    });
+}
+
+/// Peak resident set size of this process and of its largest child, in KiB.
+fn peak_rss() -> (u64, u64) {
+    let max_rss = |who| {
+        let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+        // ru_maxrss is in KiB on Linux
+        if unsafe { libc::getrusage(who, &mut usage) } == 0 {
+            usage.ru_maxrss.max(0) as u64
+        } else {
+            0
+        }
+    };
+
+    (max_rss(libc::RUSAGE_SELF), max_rss(libc::RUSAGE_CHILDREN))
+}
+
+/// The profile so far, or `None` if profiling was never enabled.
+pub fn report() -> Option<ProfileReport> {
+    let profile = PROFILE.lock().unwrap_or_else(|e| e.into_inner());
+    let profile = profile.as_ref()?;
+    let (peak_rss_kb, peak_child_rss_kb) = peak_rss();
+
+    Some(ProfileReport {
+        total_ms: ms(profile.start.elapsed()),
+        decode_ms: ms(profile.decode_time),
+        encode_ms: profile.encodes.iter().map(|e| e.ms).sum(),
+        external_ms: profile
+            .external_time
+            .iter()
+            .map(|(tool, time)| (*tool, ms(*time)))
+            .collect(),
+        peak_rss_kb,
+        peak_child_rss_kb,
+        decodes: profile.decodes.clone(),
+        operations: profile.operations.clone(),
+        encodes: profile.encodes.clone(),
+    })
+}
@@ -0,0 +1,129 @@
+use std::fs::File;
+use std::io::{stderr, BufRead, BufReader, Read, Write};
+use std::os::fd::FromRawFd;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::Serialize;
+
+use crate::core::error::FluxError;
+
+/// Where progress events are written, set up by `--progress`. Like the profile, this is global
+/// because frames are processed on other threads.
+static SINK: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressTarget {
+    Stderr,
+    /// A file descriptor opened by the parent process, e.g. `3>progress.ndjson`.
+    Fd(i32),
+}
+impl ProgressTarget {
+    /// Parses `stderr`, or the number of an open file descriptor.
+    pub fn parse(target: &str) -> Option<Self> {
+        if target == "stderr" {
+            Some(Self::Stderr)
+        } else {
+            target.parse::<i32>().ok().filter(|fd| *fd >= 0).map(Self::Fd)
+        }
+    }
+}
+
+/// A progress event, written as one line of JSON, e.g. `{"event":"frames","done":12,"total":40}`.
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    OperationStarted { operation: &'a str },
+    OperationFinished { operation: &'a str, ms: f64 },
+    EncodeStarted,
+    EncodeFinished { ms: f64 },
+    /// Frames processed so far by the current operation or encode. `total` is `null` if it is not
+    /// known, e.g. for some ffmpeg commands.
+    Frames { done: u64, total: Option<u64> },
+}
+
+/// Starts writing progress events to `target`.
+pub fn enable(target: ProgressTarget) -> Result<(), FluxError> {
+    let sink: Box<dyn Write + Send> = match target {
+        ProgressTarget::Stderr => Box::new(stderr()),
+        // stdout may be carrying the output, and stderr has a target of its own
+        ProgressTarget::Fd(fd @ 0..=2) => {
+            return Err(FluxError::ParameterError(format!(
+                "Progress cannot be written to file descriptor {fd}; use stderr for standard error"
+            )));
+        },
+        ProgressTarget::Fd(fd) => {
+            // a copy is owned instead, so that the descriptor stays open for the parent process
+            // when the sink is dropped. This also fails if the descriptor is not open.
+            let copy = unsafe { libc::dup(fd) };
+            if copy == -1 {
+                return Err(FluxError::ParameterError(format!(
+                    "Progress file descriptor {fd} is not open"
+                )));
+            }
+            Box::new(unsafe { File::from_raw_fd(copy) })
+        },
+    };
+
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+    Ok(())
+}
+
+pub fn enabled() -> bool {
+    SINK.lock().unwrap_or_else(|e| e.into_inner()).is_some()
+}
+
+/// Writes an event, if progress events are enabled. Write errors are ignored, since progress is
+/// only informational.
+pub fn emit(event: ProgressEvent) {
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = sink.as_mut()
+        && let Ok(mut json) = serde_json::to_vec(&event)
+    {
+        json.push(b'\n');
+        let _ = sink.write_all(&json);
+        let _ = sink.flush();
+    }
+}
+
+/// Counts frames as they are processed, which may be on several threads at once.
+pub struct FrameCounter {
+    done: AtomicU64,
+    total: Option<u64>,
+    enabled: bool,
+}
+impl FrameCounter {
+    pub fn new(total: Option<u64>) -> Self {
+        Self {
+            done: AtomicU64::new(0),
+            total,
+            enabled: enabled(),
+        }
+    }
+
+    pub fn tick(&self) {
+        self.set(self.done.fetch_add(1, Ordering::Relaxed) + 1);
+    }
+
+    pub fn set(&self, done: u64) {
+        if self.enabled {
+            emit(ProgressEvent::Frames {
+                done,
+                total: self.total,
+            });
+        }
+    }
+}
+
+/// Reads the output of ffmpeg's `-progress` option, reporting the frame count from each update.
+pub fn relay_ffmpeg_progress(output: impl Read, total: Option<u64>) {
+    let counter = FrameCounter::new(total);
+
+    for line in BufReader::new(output).lines().map_while(Result::ok) {
+        if let Some(frame) = line.strip_prefix("frame=")
+            && let Ok(frame) = frame.trim().parse::<u64>()
+        {
+            counter.set(frame);
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use std::fmt::Display;
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Makes temporary file names unique within this process, since several jobs may be processing the
+/// same input at once when running as a server.
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Clone)]
 pub struct TmpFile(String, String);
@@ -8,7 +13,8 @@ impl TmpFile {
     where
         S: AsRef<str> + Display,
     {
-        TmpFile(format!("/tmp/{}-flux-{name}", std::process::id()), name.to_string())
+        let n = TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        TmpFile(format!("/tmp/{}-flux-{n}-{name}", std::process::id()), name.to_string())
     }
 
     pub fn write<C>(&self, contents: C) -> std::io::Result<()>
@@ -1,7 +1,8 @@
-use std::ffi::{c_char, c_double, c_int};
+use std::ffi::{c_char, c_double, c_int, c_ulonglong};
 
 unsafe extern "C" {
     pub fn v_vips_init() -> c_int;
+    pub fn v_vips_version() -> *const c_char;
     pub fn v_transcode_to(
         input: *const u8,
         len: usize,
@@ -47,6 +48,44 @@ unsafe extern "C" {
         width: usize,
         text: *const c_char,
     ) -> c_int;
+    pub fn v_webp_save_animated(
+        input: *const u8,
+        len: usize,
+        width: c_int,
+        page_height: c_int,
+        pages: c_int,
+        delays: *const c_int,
+        loop_count: c_int,
+        lossless: c_int,
+        quality: c_int,
+        output: *mut *mut u8,
+        size: *mut usize,
+    ) -> c_int;
+    pub fn v_decode_pages(
+        input: *const u8,
+        len: usize,
+        pages: c_int,
+        pixel_limit: c_ulonglong,
+        output: *mut *mut u8,
+        size: *mut usize,
+        width: *mut c_int,
+        page_height: *mut c_int,
+        delays: *mut *mut c_int,
+        delays_len: *mut c_int,
+    ) -> c_int;
+    pub fn v_render_svg(
+        input: *const u8,
+        len: usize,
+        scale: c_double,
+        fit_width: c_int,
+        fit_height: c_int,
+        enlarge: c_int,
+        pixel_limit: c_ulonglong,
+        output: *mut *mut u8,
+        size: *mut usize,
+        width: *mut c_int,
+        height: *mut c_int,
+    ) -> c_int;
     pub fn v_canny(
         input: *const u8,
         len: usize,
@@ -1,10 +1,12 @@
 use std::ffi::{c_int, CStr, CString};
 use std::ptr::{null_mut, slice_from_raw_parts};
+use std::time::Duration;
 
 use ffi::*;
 use image::{DynamicImage, ImageBuffer, Rgba};
 
 use crate::core::error::FluxError;
+use crate::util::profile::time_external;
 
 pub mod ffi;
 
@@ -22,8 +24,14 @@ fn vips_get_error() -> String {
     unsafe { CStr::from_ptr(err).to_str().unwrap().to_owned() }
 }
 
+/// The version of libvips in use, e.g. `8.15.1`.
+pub fn vips_version() -> String {
+    crate::init();
+    unsafe { CStr::from_ptr(v_vips_version()).to_string_lossy().into_owned() }
+}
+
 pub fn vips_generate_caption(text: &str, width: usize) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let text = text_pango_safe(text);
 
@@ -32,7 +40,9 @@ pub fn vips_generate_caption(text: &str, width: usize) -> Result<DynamicImage, F
     let mut height: usize = 0;
     let c_text = CString::new(text).map_err(|e| FluxError::ParameterError(e.to_string()))?;
 
-    let res = unsafe { v_generate_caption_header(&mut buf, &mut size, &mut height, width, c_text.as_ptr()) };
+    let res = time_external("vips", || unsafe {
+        v_generate_caption_header(&mut buf, &mut size, &mut height, width, c_text.as_ptr())
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -57,7 +67,7 @@ pub fn vips_generate_motivate_text(
     text_width: usize,
     pad_height: bool,
 ) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let text = format!(
         "<span foreground=\"{}\" background=\"black\"> {} </span>",
@@ -70,7 +80,7 @@ pub fn vips_generate_motivate_text(
     let mut height: usize = 0;
     let c_text = CString::new(text).map_err(|e| FluxError::ParameterError(e.to_string()))?;
 
-    let res = unsafe {
+    let res = time_external("vips", || unsafe {
         v_generate_motivate_text(
             &mut buf,
             &mut size,
@@ -80,7 +90,7 @@ pub fn vips_generate_motivate_text(
             text_width,
             pad_height as c_int,
         )
-    };
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -108,7 +118,7 @@ pub fn vips_generate_motivate_box_blank(
 }
 
 pub fn vips_generate_meme_text(text: &str, width: usize, height: usize) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let real_text = format!("<span foreground=\"white\">{}</span>", text_pango_safe(text));
 
@@ -117,7 +127,9 @@ pub fn vips_generate_meme_text(text: &str, width: usize, height: usize) -> Resul
 
     let c_text = CString::new(real_text).map_err(|e| FluxError::ParameterError(e.to_string()))?;
 
-    let res = unsafe { v_generate_meme_text(&mut buf, &mut size, height, width, c_text.as_ptr()) };
+    let res = time_external("vips", || unsafe {
+        v_generate_meme_text(&mut buf, &mut size, height, width, c_text.as_ptr())
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -139,7 +151,9 @@ pub fn vips_transcode_to(input: &[u8], format: &str) -> Result<Vec<u8>, FluxErro
     let mut buf = std::ptr::null_mut::<u8>();
     let mut size: usize = 0;
     let format = CString::new(format).unwrap();
-    let res = unsafe { v_transcode_to(input.as_ptr(), input.len(), &mut buf, &mut size, format.as_ptr()) };
+    let res = time_external("vips", || unsafe {
+        v_transcode_to(input.as_ptr(), input.len(), &mut buf, &mut size, format.as_ptr())
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -157,7 +171,9 @@ pub fn vips_transcode_to(input: &[u8], format: &str) -> Result<Vec<u8>, FluxErro
 pub fn vips_gravity(input: &[u8], width: usize, height: usize) -> Result<DynamicImage, FluxError> {
     let mut buf = std::ptr::null_mut::<u8>();
     let mut size: usize = 0;
-    let res = unsafe { v_gravity(input.as_ptr(), input.len(), &mut buf, &mut size, width, height) };
+    let res = time_external("vips", || unsafe {
+        v_gravity(input.as_ptr(), input.len(), &mut buf, &mut size, width, height)
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!("error resizing: {}", vips_get_error())));
@@ -172,8 +188,177 @@ pub fn vips_gravity(input: &[u8], width: usize, height: usize) -> Result<Dynamic
     Ok(DynamicImage::ImageRgba8(image))
 }
 
+/// Encodes RGBA frames, stacked vertically in `input`, as an animated WebP. `delays` are in
+/// milliseconds and a `loop_count` of 0 loops forever. Without a `quality` the WebP is lossless.
+pub fn vips_webp_save_animated(
+    input: &[u8],
+    width: u32,
+    page_height: u32,
+    delays: &[i32],
+    loop_count: u16,
+    quality: Option<u8>,
+) -> Result<Vec<u8>, FluxError> {
+    crate::init();
+
+    let mut buf = null_mut();
+    let mut size: usize = 0;
+    let res = time_external("vips", || unsafe {
+        v_webp_save_animated(
+            input.as_ptr(),
+            input.len(),
+            width as c_int,
+            page_height as c_int,
+            delays.len() as c_int,
+            delays.as_ptr(),
+            loop_count as c_int,
+            quality.is_none() as c_int,
+            quality.unwrap_or(100) as c_int,
+            &mut buf,
+            &mut size,
+        )
+    });
+
+    if res != 0 {
+        return Err(FluxError::ScriptError(format!(
+            "error encoding webp: {}",
+            vips_get_error()
+        )));
+    }
+
+    let buffer = unsafe { (*slice_from_raw_parts(buf, size)).to_owned() };
+    unsafe { v_g_free(buf as *const ()) };
+
+    Ok(buffer)
+}
+
+/// Decodes every page of an image (such as the frames of an animated AVIF), or only the first
+/// `frame_limit` pages, along with the delay of each page if it has one. The pixel limit is checked
+/// before anything is decoded.
+pub fn vips_decode_pages(
+    input: &[u8],
+    frame_limit: Option<u64>,
+    pixel_limit: Option<u64>,
+) -> Result<Vec<(DynamicImage, Option<Duration>)>, FluxError> {
+    crate::init();
+
+    let mut buf = null_mut();
+    let mut size: usize = 0;
+    let mut width: c_int = 0;
+    let mut page_height: c_int = 0;
+    let mut delays = null_mut();
+    let mut delays_len: c_int = 0;
+    let pages = frame_limit.map(|l| l.min(c_int::MAX as u64) as c_int).unwrap_or(-1);
+
+    let res = time_external("vips", || unsafe {
+        v_decode_pages(
+            input.as_ptr(),
+            input.len(),
+            pages,
+            pixel_limit.unwrap_or(0),
+            &mut buf,
+            &mut size,
+            &mut width,
+            &mut page_height,
+            &mut delays,
+            &mut delays_len,
+        )
+    });
+
+    if res == -2
+        && let Some(limit) = pixel_limit
+    {
+        return Err(FluxError::PixelLimitExceeded(limit));
+    } else if res != 0 {
+        return Err(FluxError::CorruptInput(format!("error decoding: {}", vips_get_error())));
+    }
+
+    let buffer = unsafe { (*slice_from_raw_parts(buf, size)).to_owned() };
+    unsafe { v_g_free(buf as *const ()) };
+    let delays = if delays.is_null() {
+        vec![]
+    } else {
+        let d = unsafe { (*slice_from_raw_parts(delays, delays_len as usize)).to_owned() };
+        unsafe { v_g_free(delays as *const ()) };
+        d
+    };
+
+    let (width, page_height) = (width as u32, page_height as u32);
+    let page_size = width as usize * page_height as usize * 4;
+    if page_size == 0 {
+        return Err(FluxError::CorruptInput("Input has no frames".to_owned()));
+    }
+
+    let pages = buffer
+        .chunks_exact(page_size)
+        .enumerate()
+        .map(|(i, page)| {
+            let image = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width, page_height, page.to_vec())
+                .ok_or(FluxError::CorruptInput("Decoded page has the wrong size".to_owned()))?;
+            let delay = delays.get(i).map(|d| Duration::from_millis((*d).max(0) as u64));
+
+            Ok((DynamicImage::ImageRgba8(image), delay))
+        })
+        .collect::<Result<Vec<_>, FluxError>>()?;
+
+    Ok(pages)
+}
+
+/// Renders an SVG at `scale` (where 1 is 72 dpi), or to fit within `fit` if it is larger than that.
+/// With `enlarge`, the SVG is rendered to fit within `fit` whatever its size. The pixel limit is
+/// checked before anything is rendered.
+pub fn vips_render_svg(
+    input: &[u8],
+    scale: f64,
+    fit: Option<(u32, u32)>,
+    enlarge: bool,
+    pixel_limit: Option<u64>,
+) -> Result<DynamicImage, FluxError> {
+    crate::init();
+
+    let mut buf = null_mut();
+    let mut size: usize = 0;
+    let mut width: c_int = 0;
+    let mut height: c_int = 0;
+    let (fit_width, fit_height) = fit.unwrap_or((0, 0));
+
+    let res = time_external("vips", || unsafe {
+        v_render_svg(
+            input.as_ptr(),
+            input.len(),
+            scale,
+            fit_width.min(c_int::MAX as u32) as c_int,
+            fit_height.min(c_int::MAX as u32) as c_int,
+            enlarge as c_int,
+            pixel_limit.unwrap_or(0),
+            &mut buf,
+            &mut size,
+            &mut width,
+            &mut height,
+        )
+    });
+
+    if res == -2
+        && let Some(limit) = pixel_limit
+    {
+        return Err(FluxError::PixelLimitExceeded(limit));
+    } else if res != 0 {
+        return Err(FluxError::CorruptInput(format!(
+            "error rendering svg: {}",
+            vips_get_error()
+        )));
+    }
+
+    let buffer = unsafe { (*slice_from_raw_parts(buf, size)).to_owned() };
+    unsafe { v_g_free(buf as *const ()) };
+
+    let image = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width as u32, height as u32, buffer)
+        .ok_or(FluxError::CorruptInput("Rendered SVG has the wrong size".to_owned()))?;
+
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
 pub fn vips_generate_heart_locket_text(text: &str, width: usize, height: usize) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let text = format!(
         "<span foreground=\"black\" background=\"white\"> {} </span>",
@@ -184,7 +369,9 @@ pub fn vips_generate_heart_locket_text(text: &str, width: usize, height: usize)
     let mut size: usize = 0;
     let c_text = CString::new(text).map_err(|e| FluxError::ParameterError(e.to_string()))?;
 
-    let res = unsafe { v_generate_heart_locket_text(&mut buf, &mut size, height, width, c_text.as_ptr()) };
+    let res = time_external("vips", || unsafe {
+        v_generate_heart_locket_text(&mut buf, &mut size, height, width, c_text.as_ptr())
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -203,11 +390,11 @@ pub fn vips_generate_heart_locket_text(text: &str, width: usize, height: usize)
 }
 
 pub fn vips_canny(input: &[u8], width: usize, height: usize, sigma: f64) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let mut buf = std::ptr::null_mut::<u8>();
     let mut size: usize = 0;
-    let res = unsafe {
+    let res = time_external("vips", || unsafe {
         v_canny(
             input.as_ptr(),
             input.len(),
@@ -217,7 +404,7 @@ pub fn vips_canny(input: &[u8], width: usize, height: usize, sigma: f64) -> Resu
             &mut size,
             sigma,
         )
-    };
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -237,11 +424,11 @@ pub fn vips_canny(input: &[u8], width: usize, height: usize, sigma: f64) -> Resu
 }
 
 pub fn vips_sobel(input: &[u8], width: usize, height: usize) -> Result<DynamicImage, FluxError> {
-    unsafe { v_vips_init() };
+    crate::init();
 
     let mut buf = std::ptr::null_mut::<u8>();
     let mut size: usize = 0;
-    let res = unsafe {
+    let res = time_external("vips", || unsafe {
         v_sobel(
             input.as_ptr(),
             input.len(),
@@ -250,7 +437,7 @@ pub fn vips_sobel(input: &[u8], width: usize, height: usize) -> Result<DynamicIm
             &mut buf,
             &mut size,
         )
-    };
+    });
 
     if res != 0 {
         return Err(FluxError::ScriptError(format!(
@@ -1,3 +1,4 @@
+#include <string.h>
 #include <vips/vips.h>
 #include "flux_v_util.c"
 
@@ -24,4 +25,143 @@ int v_gravity(char *input, size_t len, char **output, size_t *size, size_t width
 	g_object_unref(image);
 
 	return 0;
-}
\ No newline at end of file
+}
+
+int v_webp_save_animated(char *input, size_t len, int width, int page_height, int pages, int *delays, int loop, int lossless, int quality, char **output, size_t *size)
+{
+	VipsImage *image = vips_image_new_from_memory(input, len, width, page_height * pages, 4, VIPS_FORMAT_UCHAR);
+
+	if (image == NULL)
+	{
+		return -1;
+	}
+
+	vips_image_set_int(image, "page-height", page_height);
+	vips_image_set_array_int(image, "delay", delays, pages);
+	vips_image_set_int(image, "loop", loop);
+
+	RETURN_NONZERO(
+		vips_webpsave_buffer(image, (void **)output, size, "lossless", lossless, "Q", quality, NULL))
+
+	g_object_unref(image);
+
+	return 0;
+}
+
+
+// Decodes up to `pages` pages (or all of them if `pages` is negative) to RGBA, stacked vertically.
+// Returns -2 without decoding anything if the image is larger than `pixel_limit` (if non-zero).
+int v_decode_pages(char *input, size_t len, int pages, unsigned long long pixel_limit, char **output, size_t *size, int *width, int *page_height, int **delays, int *delays_len)
+{
+	VipsImage *image = vips_image_new_from_buffer(input, len, "", NULL);
+
+	if (image == NULL)
+	{
+		return -1;
+	}
+
+	int n = vips_image_get_n_pages(image);
+	if (pages >= 0 && pages < n)
+	{
+		n = pages;
+	}
+	g_object_unref(image);
+
+	image = vips_image_new_from_buffer(input, len, "", "n", n, NULL);
+
+	if (image == NULL)
+	{
+		return -1;
+	}
+
+	// only the header has been read so far
+	if (pixel_limit > 0 && (unsigned long long)vips_image_get_width(image) * vips_image_get_height(image) > pixel_limit)
+	{
+		g_object_unref(image);
+		return -2;
+	}
+
+	*width = vips_image_get_width(image);
+	*page_height = vips_image_get_page_height(image);
+
+	int *image_delays;
+	int image_delays_len;
+	if (vips_image_get_typeof(image, "delay") && !vips_image_get_array_int(image, "delay", &image_delays, &image_delays_len))
+	{
+		*delays = g_malloc(sizeof(int) * image_delays_len);
+		memcpy(*delays, image_delays, sizeof(int) * image_delays_len);
+		*delays_len = image_delays_len;
+	}
+	else
+	{
+		*delays = NULL;
+		*delays_len = 0;
+	}
+
+	RETURN_NONZERO(
+		vips_colourspace(image, &image, VIPS_INTERPRETATION_sRGB, NULL))
+
+	if (!vips_image_hasalpha(image))
+	{
+		RETURN_NONZERO(
+			vips_addalpha(image, &image, NULL))
+	}
+
+	RETURN_NONZERO(
+		vips_cast(image, &image, VIPS_FORMAT_UCHAR, NULL))
+
+	*output = vips_image_write_to_memory(image, size);
+
+	g_object_unref(image);
+
+	return 0;
+}
+
+
+// Renders an SVG at `scale` (1 being 72 dpi). If `fit_width` is non-zero, the SVG is instead rendered to fit within
+// `fit_width` x `fit_height` when it is larger than that, or whatever its size if `enlarge` is set. Returns -2 without
+// rendering anything if the output would be larger than `pixel_limit` (if non-zero).
+int v_render_svg(char *input, size_t len, double scale, int fit_width, int fit_height, int enlarge, unsigned long long pixel_limit, char **output, size_t *size, int *width, int *height)
+{
+	VipsImage *image;
+
+	RETURN_NONZERO(
+		vips_svgload_buffer(input, len, &image, "scale", scale, NULL))
+
+	if (fit_width > 0 && (enlarge || vips_image_get_width(image) > fit_width || vips_image_get_height(image) > fit_height))
+	{
+		g_object_unref(image);
+
+		// thumbnailing an SVG renders it at the new size, rather than resizing the rendered image
+		RETURN_NONZERO(
+			vips_thumbnail_buffer(input, len, &image, fit_width, "height", fit_height, NULL))
+	}
+
+	// only the header has been read so far
+	if (pixel_limit > 0 && (unsigned long long)vips_image_get_width(image) * vips_image_get_height(image) > pixel_limit)
+	{
+		g_object_unref(image);
+		return -2;
+	}
+
+	*width = vips_image_get_width(image);
+	*height = vips_image_get_height(image);
+
+	RETURN_NONZERO(
+		vips_colourspace(image, &image, VIPS_INTERPRETATION_sRGB, NULL))
+
+	if (!vips_image_hasalpha(image))
+	{
+		RETURN_NONZERO(
+			vips_addalpha(image, &image, NULL))
+	}
+
+	RETURN_NONZERO(
+		vips_cast(image, &image, VIPS_FORMAT_UCHAR, NULL))
+
+	*output = vips_image_write_to_memory(image, size);
+
+	g_object_unref(image);
+
+	return 0;
+}
@@ -27,6 +27,11 @@ int v_transcode_to(char *input, size_t len, char **output, size_t *size, char *f
 	return 0;
 }
 
+const char *v_vips_version()
+{
+	return vips_version_string();
+}
+
 char *v_get_error()
 {
 	return vips_error_buffer();